@@ -0,0 +1,86 @@
+use crate::utils::{
+    camera::Camera,
+    color::Color,
+    hittable::{Hittable, HittableList},
+    material::{Dielectric, DiffuseLight, Lambertian, ThinFilm},
+    options::RenderOptions,
+    point3::Point3,
+    sphere::Sphere,
+    vec3::Vec3,
+};
+
+pub fn run(options: &RenderOptions) {
+    // Materials
+    let material_ground = Lambertian {
+        albedo: Color::new(0.15, 0.15, 0.18),
+    };
+    // Soap bubbles floating in air, so the air on both sides of the film has
+    // an index of 1. A film drains thinner over time: thick ones show pale
+    // pastels, thinner ones saturated blues and golds.
+    let soap_bubble = |thickness: f64| Dielectric {
+        refraction_index: 1.,
+        absorption: Color::zero(),
+        dispersion: None,
+        thin_film: Some(ThinFilm {
+            thickness,
+            refraction_index: 1.33,
+        }),
+    };
+    let material_thick = soap_bubble(900.);
+    let material_medium = soap_bubble(500.);
+    let material_thin = soap_bubble(250.);
+    let material_light = DiffuseLight {
+        emit: Color::new(6., 6., 6.),
+    };
+
+    let ground = Sphere {
+        center: Point3::new(0., -100.5, -3.),
+        radius: 100.,
+        material: Some(&material_ground),
+    };
+    let thick = Sphere {
+        center: Point3::new(-1.2, 0.1, -3.2),
+        radius: 0.5,
+        material: Some(&material_thick),
+    };
+    let medium = Sphere {
+        center: Point3::new(0., 0.3, -3.),
+        radius: 0.6,
+        material: Some(&material_medium),
+    };
+    let thin = Sphere {
+        center: Point3::new(1.2, 0.05, -3.2),
+        radius: 0.45,
+        material: Some(&material_thin),
+    };
+    let light = Sphere {
+        center: Point3::new(-2., 3., -1.),
+        radius: 0.8,
+        material: Some(&material_light),
+    };
+
+    // World
+    let mut world: HittableList<dyn Hittable> = HittableList::default();
+    world.add(&ground);
+    world.add(&thick);
+    world.add(&medium);
+    world.add(&thin);
+    world.add(&light);
+
+    let mut lights: HittableList<dyn Hittable> = HittableList::default();
+    lights.add(&light);
+
+    let mut camera = Camera::initialize(
+        16. / 9.,
+        400,
+        100,
+        50,
+        50.,
+        Point3::new(0., 0., 0.),
+        Point3::new(0., 0., -3.),
+        Vec3::new(0., 1., 0.),
+    );
+    options.configure(&mut camera);
+
+    camera.render(&world, &lights);
+}
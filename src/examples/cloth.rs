@@ -0,0 +1,107 @@
+use crate::utils::{
+    camera::Camera,
+    color::Color,
+    hittable::{Hittable, HittableList},
+    material::{DiffuseLight, Lambertian, OrenNayar, Velvet},
+    options::RenderOptions,
+    point3::Point3,
+    sphere::Sphere,
+    vec3::Vec3,
+};
+
+pub fn run(options: &RenderOptions) {
+    // Materials
+    let material_ground = OrenNayar {
+        albedo: Color::new(0.5, 0.5, 0.5),
+        sigma: 30.,
+    };
+    // the same albedo from smooth to very rough, then two fabrics
+    let material_smooth = Lambertian {
+        albedo: Color::new(0.7, 0.45, 0.3),
+    };
+    let material_clay = OrenNayar {
+        albedo: Color::new(0.7, 0.45, 0.3),
+        sigma: 20.,
+    };
+    let material_plaster = OrenNayar {
+        albedo: Color::new(0.7, 0.45, 0.3),
+        sigma: 60.,
+    };
+    let material_velvet = Velvet {
+        albedo: Color::new(0.25, 0.02, 0.08),
+        sheen: Color::new(0.9, 0.5, 0.6),
+        roughness: 0.4,
+    };
+    let material_satin = Velvet {
+        albedo: Color::new(0.05, 0.1, 0.3),
+        sheen: Color::new(0.6, 0.7, 1.),
+        roughness: 0.8,
+    };
+    let material_light = DiffuseLight {
+        emit: Color::new(8., 8., 8.),
+    };
+
+    let ground = Sphere {
+        center: Point3::new(0., -100.5, -3.),
+        radius: 100.,
+        material: Some(&material_ground),
+    };
+    let smooth = Sphere {
+        center: Point3::new(-1.65, 0., -3.),
+        radius: 0.5,
+        material: Some(&material_smooth),
+    };
+    let clay = Sphere {
+        center: Point3::new(-0.55, 0., -3.),
+        radius: 0.5,
+        material: Some(&material_clay),
+    };
+    let plaster = Sphere {
+        center: Point3::new(0.55, 0., -3.),
+        radius: 0.5,
+        material: Some(&material_plaster),
+    };
+    let velvet = Sphere {
+        center: Point3::new(1.65, 0., -3.),
+        radius: 0.5,
+        material: Some(&material_velvet),
+    };
+    let satin = Sphere {
+        center: Point3::new(0., 0.5, -4.6),
+        radius: 1.,
+        material: Some(&material_satin),
+    };
+    // behind the camera, where rough surfaces send light back
+    let light = Sphere {
+        center: Point3::new(-1., 2., 1.),
+        radius: 0.6,
+        material: Some(&material_light),
+    };
+
+    // World
+    let mut world: HittableList<dyn Hittable> = HittableList::default();
+    world.add(&ground);
+    world.add(&smooth);
+    world.add(&clay);
+    world.add(&plaster);
+    world.add(&velvet);
+    world.add(&satin);
+    world.add(&light);
+
+    let mut lights: HittableList<dyn Hittable> = HittableList::default();
+    lights.add(&light);
+
+    let mut camera = Camera::initialize(
+        16. / 9.,
+        400,
+        100,
+        50,
+        50.,
+        Point3::new(0., 0., 0.),
+        Point3::new(0., 0., -3.),
+        Vec3::new(0., 1., 0.),
+    );
+    options.configure(&mut camera);
+
+    camera.render(&world, &lights);
+}
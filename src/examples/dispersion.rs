@@ -0,0 +1,100 @@
+use crate::utils::{
+    camera::Camera,
+    color::Color,
+    hittable::{Hittable, HittableList},
+    material::{Dielectric, DiffuseLight, Principled},
+    options::RenderOptions,
+    point3::Point3,
+    sdf::{SdfObject, TriangularPrism},
+    spectrum::Dispersion,
+    sphere::Sphere,
+    texture::{CheckerTexture, SolidColor, TextureParam},
+    vec3::Vec3,
+};
+
+// Best rendered with --spectral; in RGB the glass refracts every color alike.
+pub fn run(options: &RenderOptions) {
+    // Textures
+    let black = SolidColor {
+        color: Color::new(0.05, 0.05, 0.05),
+    };
+    let white = SolidColor {
+        color: Color::new(0.9, 0.9, 0.9),
+    };
+    // sharp edges make the color fringes easy to see
+    let checker = CheckerTexture {
+        scale: 0.25,
+        even: &black,
+        odd: &white,
+    };
+
+    // Materials
+    let material_ground = Principled {
+        base_color: TextureParam::Texture(&checker),
+        roughness: TextureParam::scalar(1.),
+        ..Default::default()
+    };
+    let material_flint = Dielectric {
+        refraction_index: 1.78,
+        absorption: Color::zero(),
+        dispersion: Some(Dispersion::SF11),
+        thin_film: None,
+    };
+    let material_diamond = Dielectric {
+        refraction_index: 2.42,
+        absorption: Color::zero(),
+        dispersion: Some(Dispersion::DIAMOND),
+        thin_film: None,
+    };
+    let material_light = DiffuseLight {
+        emit: Color::new(6., 6., 6.),
+    };
+
+    // Shapes
+    let prism = TriangularPrism {
+        center: Point3::new(0., 0.1, -3.),
+        size: 0.6,
+        half_length: 1.4,
+    };
+
+    let ground = Sphere {
+        center: Point3::new(0., -100.5, -3.),
+        radius: 100.,
+        material: Some(&material_ground),
+    };
+    let prism = SdfObject::new(&prism, &material_flint);
+    let diamond = Sphere {
+        center: Point3::new(1., -0.2, -2.),
+        radius: 0.3,
+        material: Some(&material_diamond),
+    };
+    let light = Sphere {
+        center: Point3::new(-2., 3., -1.),
+        radius: 0.6,
+        material: Some(&material_light),
+    };
+
+    // World
+    let mut world: HittableList<dyn Hittable> = HittableList::default();
+    world.add(&ground);
+    world.add(&prism);
+    world.add(&diamond);
+    world.add(&light);
+
+    let mut lights: HittableList<dyn Hittable> = HittableList::default();
+    lights.add(&light);
+
+    let mut camera = Camera::initialize(
+        16. / 9.,
+        400,
+        100,
+        50,
+        50.,
+        Point3::new(0., 0.3, 0.),
+        Point3::new(0., 0., -3.),
+        Vec3::new(0., 1., 0.),
+    );
+    options.configure(&mut camera);
+
+    camera.render(&world, &lights);
+}
@@ -0,0 +1,114 @@
+use crate::utils::{
+    camera::Camera,
+    color::Color,
+    hittable::{Hittable, HittableList},
+    material::{absorption_from_color, Dielectric, DiffuseLight, Lambertian, RoughDielectric},
+    options::RenderOptions,
+    point3::Point3,
+    sphere::Sphere,
+    vec3::Vec3,
+};
+
+pub fn run(options: &RenderOptions) {
+    // Materials
+    let material_ground = Lambertian {
+        albedo: Color::new(0.5, 0.5, 0.5),
+    };
+    let material_backdrop = Lambertian {
+        albedo: Color::new(0.7, 0.2, 0.1),
+    };
+    // Schlick glass next to exact-Fresnel glass of increasing roughness
+    let material_smooth = Dielectric {
+        refraction_index: 1.5,
+        absorption: Color::zero(),
+        dispersion: None,
+        thin_film: None,
+    };
+    let material_polished = RoughDielectric::new(1.5, 0.);
+    let material_satin = RoughDielectric::new(1.5, 0.3);
+    let material_frosted = RoughDielectric::new(1.5, 0.6);
+    // the same green tint; the bigger drop is thicker and comes out darker
+    let material_tinted = RoughDielectric {
+        absorption: absorption_from_color(Color::new(0.4, 0.8, 0.5), 0.3),
+        ..RoughDielectric::new(1.5, 0.)
+    };
+    let material_light = DiffuseLight {
+        emit: Color::new(8., 8., 8.),
+    };
+
+    let ground = Sphere {
+        center: Point3::new(0., -100.5, -3.),
+        radius: 100.,
+        material: Some(&material_ground),
+    };
+    // something behind the glass to see refracted
+    let backdrop = Sphere {
+        center: Point3::new(0., 0.2, -5.5),
+        radius: 1.2,
+        material: Some(&material_backdrop),
+    };
+    let smooth = Sphere {
+        center: Point3::new(-1.65, 0., -3.),
+        radius: 0.5,
+        material: Some(&material_smooth),
+    };
+    let polished = Sphere {
+        center: Point3::new(-0.55, 0., -3.),
+        radius: 0.5,
+        material: Some(&material_polished),
+    };
+    let satin = Sphere {
+        center: Point3::new(0.55, 0., -3.),
+        radius: 0.5,
+        material: Some(&material_satin),
+    };
+    let frosted = Sphere {
+        center: Point3::new(1.65, 0., -3.),
+        radius: 0.5,
+        material: Some(&material_frosted),
+    };
+    let small_drop = Sphere {
+        center: Point3::new(-0.45, -0.35, -2.),
+        radius: 0.12,
+        material: Some(&material_tinted),
+    };
+    let large_drop = Sphere {
+        center: Point3::new(0.45, -0.2, -2.),
+        radius: 0.28,
+        material: Some(&material_tinted),
+    };
+    let light = Sphere {
+        center: Point3::new(-2., 3., -1.),
+        radius: 0.6,
+        material: Some(&material_light),
+    };
+
+    // World
+    let mut world: HittableList<dyn Hittable> = HittableList::default();
+    world.add(&ground);
+    world.add(&backdrop);
+    world.add(&smooth);
+    world.add(&polished);
+    world.add(&satin);
+    world.add(&frosted);
+    world.add(&small_drop);
+    world.add(&large_drop);
+    world.add(&light);
+
+    let mut lights: HittableList<dyn Hittable> = HittableList::default();
+    lights.add(&light);
+
+    let mut camera = Camera::initialize(
+        16. / 9.,
+        400,
+        100,
+        50,
+        50.,
+        Point3::new(0., 0., 0.),
+        Point3::new(0., 0., -3.),
+        Vec3::new(0., 1., 0.),
+    );
+    options.configure(&mut camera);
+
+    camera.render(&world, &lights);
+}
@@ -0,0 +1,113 @@
+use crate::utils::{
+    camera::Camera,
+    color::Color,
+    hittable::{Hittable, HittableList},
+    material::{ComplexIor, Dielectric, DiffuseLight, GgxConductor, Lambertian, ThinFilm},
+    options::RenderOptions,
+    point3::Point3,
+    sphere::Sphere,
+    vec3::Vec3,
+};
+
+pub fn run(options: &RenderOptions) {
+    // Materials
+    let material_ground = Lambertian {
+        albedo: Color::new(0.2, 0.2, 0.25),
+    };
+    // soap film with air on both sides
+    let material_bubble = Dielectric {
+        refraction_index: 1.,
+        absorption: Color::zero(),
+        dispersion: None,
+        thin_film: Some(ThinFilm {
+            thickness: 450.,
+            refraction_index: 1.33,
+        }),
+    };
+    // quarter-wave magnesium fluoride coating, which cuts reflections from
+    // glass and leaves the faint purple of a camera lens
+    let material_lens = Dielectric {
+        refraction_index: 1.5,
+        absorption: Color::zero(),
+        dispersion: None,
+        thin_film: Some(ThinFilm {
+            thickness: 100.,
+            refraction_index: 1.38,
+        }),
+    };
+    // oxide layers grown on heated steel: straw, then blue
+    let material_straw = GgxConductor {
+        thin_film: Some(ThinFilm {
+            thickness: 30.,
+            refraction_index: 2.4,
+        }),
+        ..GgxConductor::new(ComplexIor::IRON, 0.2)
+    };
+    let material_blued = GgxConductor {
+        thin_film: Some(ThinFilm {
+            thickness: 70.,
+            refraction_index: 2.4,
+        }),
+        ..GgxConductor::new(ComplexIor::IRON, 0.2)
+    };
+    let material_light = DiffuseLight {
+        emit: Color::new(8., 8., 8.),
+    };
+
+    let ground = Sphere {
+        center: Point3::new(0., -100.5, -3.),
+        radius: 100.,
+        material: Some(&material_ground),
+    };
+    let bubble = Sphere {
+        center: Point3::new(-1.65, 0.1, -3.),
+        radius: 0.5,
+        material: Some(&material_bubble),
+    };
+    let lens = Sphere {
+        center: Point3::new(-0.55, 0., -3.),
+        radius: 0.5,
+        material: Some(&material_lens),
+    };
+    let straw = Sphere {
+        center: Point3::new(0.55, 0., -3.),
+        radius: 0.5,
+        material: Some(&material_straw),
+    };
+    let blued = Sphere {
+        center: Point3::new(1.65, 0., -3.),
+        radius: 0.5,
+        material: Some(&material_blued),
+    };
+    let light = Sphere {
+        center: Point3::new(-2., 3., -1.),
+        radius: 0.6,
+        material: Some(&material_light),
+    };
+
+    // World
+    let mut world: HittableList<dyn Hittable> = HittableList::default();
+    world.add(&ground);
+    world.add(&bubble);
+    world.add(&lens);
+    world.add(&straw);
+    world.add(&blued);
+    world.add(&light);
+
+    let mut lights: HittableList<dyn Hittable> = HittableList::default();
+    lights.add(&light);
+
+    let mut camera = Camera::initialize(
+        16. / 9.,
+        400,
+        100,
+        50,
+        50.,
+        Point3::new(0., 0., 0.),
+        Point3::new(0., 0., -3.),
+        Vec3::new(0., 1., 0.),
+    );
+    options.configure(&mut camera);
+
+    camera.render(&world, &lights);
+}
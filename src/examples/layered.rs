@@ -0,0 +1,124 @@
+use crate::utils::{
+    camera::Camera,
+    color::Color,
+    hittable::{Hittable, HittableList},
+    material::{
+        absorption_from_color, Coated, ComplexIor, DiffuseLight, GgxConductor, Lambertian, Mix,
+    },
+    options::RenderOptions,
+    point3::Point3,
+    sphere::Sphere,
+    texture::{CheckerTexture, SolidColor, TextureParam},
+    vec3::Vec3,
+};
+
+pub fn run(options: &RenderOptions) {
+    // Textures
+    let none = SolidColor {
+        color: Color::zero(),
+    };
+    let all = SolidColor {
+        color: Color::new(1., 1., 1.),
+    };
+    let tiles = CheckerTexture {
+        scale: 0.5,
+        even: &none,
+        odd: &all,
+    };
+
+    // Materials
+    let material_stone = Lambertian {
+        albedo: Color::new(0.5, 0.5, 0.45),
+    };
+    let material_steel = GgxConductor::new(ComplexIor::IRON, 0.2);
+    // alternating matte and polished tiles
+    let material_ground = Mix {
+        first: &material_stone,
+        second: &material_steel,
+        amount: TextureParam::Texture(&tiles),
+    };
+
+    let material_red = Lambertian {
+        albedo: Color::new(0.7, 0.05, 0.05),
+    };
+    let material_car_paint = Coated::new(&material_red, 1.5);
+    let material_wood = Lambertian {
+        albedo: Color::new(0.6, 0.4, 0.25),
+    };
+    // amber varnish that deepens the wood's color
+    let material_varnished = Coated {
+        absorption: absorption_from_color(Color::new(0.9, 0.7, 0.4), 1.),
+        ..Coated::new(&material_wood, 1.5)
+    };
+    let material_copper = GgxConductor::new(ComplexIor::COPPER, 0.4);
+    let material_lacquered = Coated::new(&material_copper, 1.5);
+    // half brushed copper, half white paint, picked anew at every hit
+    let material_white = Lambertian {
+        albedo: Color::new(0.8, 0.8, 0.8),
+    };
+    let material_blend = Mix {
+        first: &material_white,
+        second: &material_copper,
+        amount: TextureParam::scalar(0.5),
+    };
+    let material_light = DiffuseLight {
+        emit: Color::new(8., 8., 8.),
+    };
+
+    let ground = Sphere {
+        center: Point3::new(0., -100.5, -3.),
+        radius: 100.,
+        material: Some(&material_ground),
+    };
+    let car_paint = Sphere {
+        center: Point3::new(-1.65, 0., -3.),
+        radius: 0.5,
+        material: Some(&material_car_paint),
+    };
+    let varnished = Sphere {
+        center: Point3::new(-0.55, 0., -3.),
+        radius: 0.5,
+        material: Some(&material_varnished),
+    };
+    let lacquered = Sphere {
+        center: Point3::new(0.55, 0., -3.),
+        radius: 0.5,
+        material: Some(&material_lacquered),
+    };
+    let blend = Sphere {
+        center: Point3::new(1.65, 0., -3.),
+        radius: 0.5,
+        material: Some(&material_blend),
+    };
+    let light = Sphere {
+        center: Point3::new(-2., 3., -1.),
+        radius: 0.6,
+        material: Some(&material_light),
+    };
+
+    // World
+    let mut world: HittableList<dyn Hittable> = HittableList::default();
+    world.add(&ground);
+    world.add(&car_paint);
+    world.add(&varnished);
+    world.add(&lacquered);
+    world.add(&blend);
+    world.add(&light);
+
+    let mut lights: HittableList<dyn Hittable> = HittableList::default();
+    lights.add(&light);
+
+    let mut camera = Camera::initialize(
+        16. / 9.,
+        400,
+        100,
+        50,
+        50.,
+        Point3::new(0., 0., 0.),
+        Point3::new(0., 0., -3.),
+        Vec3::new(0., 1., 0.),
+    );
+    options.configure(&mut camera);
+
+    camera.render(&world, &lights);
+}
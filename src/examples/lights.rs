@@ -0,0 +1,94 @@
+use crate::utils::{
+    camera::Camera,
+    color::Color,
+    hittable::{Hittable, HittableList},
+    material::{Dielectric, DiffuseLight, Lambertian, Metal},
+    options::RenderOptions,
+    point3::Point3,
+    sphere::Sphere,
+    vec3::Vec3,
+};
+
+pub fn run(options: &RenderOptions) {
+    // Materials
+    let material_ground = Lambertian {
+        albedo: Color::new(0.8, 0.8, 0.8),
+    };
+    let material_center = Lambertian {
+        albedo: Color::new(0.1, 0.2, 0.5),
+    };
+    let material_left = Dielectric {
+        refraction_index: 1.5,
+        absorption: Color::zero(),
+        dispersion: None,
+        thin_film: None,
+    };
+    let material_right = Metal::new(Color::new(0.8, 0.6, 0.2), 0.3);
+    let material_light = DiffuseLight {
+        emit: Color::new(60., 55., 45.),
+    };
+    let material_fill = DiffuseLight {
+        emit: Color::new(3., 4., 6.),
+    };
+
+    let ground = Sphere {
+        center: Point3::new(0., -100.5, -2.),
+        radius: 100.0,
+        material: Some(&material_ground),
+    };
+    let center = Sphere {
+        center: Point3::new(0., 0., -2.2),
+        radius: 0.5,
+        material: Some(&material_center),
+    };
+    let left = Sphere {
+        center: Point3::new(-1.1, 0., -2.),
+        radius: 0.5,
+        material: Some(&material_left),
+    };
+    let right = Sphere {
+        center: Point3::new(1.1, 0., -2.),
+        radius: 0.5,
+        material: Some(&material_right),
+    };
+    // a small, bright key light and a larger, dim fill light
+    let key_light = Sphere {
+        center: Point3::new(0.6, 1.4, -1.4),
+        radius: 0.1,
+        material: Some(&material_light),
+    };
+    let fill_light = Sphere {
+        center: Point3::new(-3., 2., 0.5),
+        radius: 1.,
+        material: Some(&material_fill),
+    };
+
+    // World
+    let mut world: HittableList<dyn Hittable> = HittableList::default();
+    world.add(&ground);
+    world.add(&center);
+    world.add(&left);
+    world.add(&right);
+    world.add(&key_light);
+    world.add(&fill_light);
+
+    // Lights, sampled directly at every diffuse bounce
+    let mut lights: HittableList<dyn Hittable> = HittableList::default();
+    lights.add(&key_light);
+    lights.add(&fill_light);
+
+    let mut camera = Camera::initialize(
+        16. / 9.,
+        400,
+        100,
+        50,
+        50.,
+        Point3::new(0., 0., 0.),
+        Point3::new(0., 0., -2.),
+        Vec3::new(0., 1., 0.),
+    );
+    camera.background = Some(Color::zero());
+    options.configure(&mut camera);
+
+    camera.render(&world, &lights);
+}
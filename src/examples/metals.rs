@@ -0,0 +1,89 @@
+use crate::utils::{
+    camera::Camera,
+    color::Color,
+    hittable::{Hittable, HittableList},
+    material::{ComplexIor, DiffuseLight, GgxConductor, Lambertian},
+    options::RenderOptions,
+    point3::Point3,
+    sphere::Sphere,
+    vec3::Vec3,
+};
+
+pub fn run(options: &RenderOptions) {
+    // Materials
+    let material_ground = Lambertian {
+        albedo: Color::new(0.5, 0.5, 0.5),
+    };
+    // front row gets rougher from left to right, back row is anisotropic
+    let material_gold = GgxConductor::new(ComplexIor::GOLD, 0.);
+    let material_copper = GgxConductor::new(ComplexIor::COPPER, 0.25);
+    let material_aluminum = GgxConductor::new(ComplexIor::ALUMINUM, 0.45);
+    let material_silver = GgxConductor::new(ComplexIor::SILVER, 0.7);
+    let material_brushed = GgxConductor::anisotropic(ComplexIor::IRON, 0.15, 0.6);
+    let material_light = DiffuseLight {
+        emit: Color::new(8., 8., 8.),
+    };
+
+    let ground = Sphere {
+        center: Point3::new(0., -100.5, -3.),
+        radius: 100.,
+        material: Some(&material_ground),
+    };
+    let gold = Sphere {
+        center: Point3::new(-1.65, 0., -3.),
+        radius: 0.5,
+        material: Some(&material_gold),
+    };
+    let copper = Sphere {
+        center: Point3::new(-0.55, 0., -3.),
+        radius: 0.5,
+        material: Some(&material_copper),
+    };
+    let aluminum = Sphere {
+        center: Point3::new(0.55, 0., -3.),
+        radius: 0.5,
+        material: Some(&material_aluminum),
+    };
+    let silver = Sphere {
+        center: Point3::new(1.65, 0., -3.),
+        radius: 0.5,
+        material: Some(&material_silver),
+    };
+    let brushed = Sphere {
+        center: Point3::new(0., 0.5, -4.6),
+        radius: 1.,
+        material: Some(&material_brushed),
+    };
+    let light = Sphere {
+        center: Point3::new(-2., 3., -1.),
+        radius: 0.6,
+        material: Some(&material_light),
+    };
+
+    // World
+    let mut world: HittableList<dyn Hittable> = HittableList::default();
+    world.add(&ground);
+    world.add(&gold);
+    world.add(&copper);
+    world.add(&aluminum);
+    world.add(&silver);
+    world.add(&brushed);
+    world.add(&light);
+
+    let mut lights: HittableList<dyn Hittable> = HittableList::default();
+    lights.add(&light);
+
+    let mut camera = Camera::initialize(
+        16. / 9.,
+        400,
+        100,
+        50,
+        50.,
+        Point3::new(0., 0., 0.),
+        Point3::new(0., 0., -3.),
+        Vec3::new(0., 1., 0.),
+    );
+    options.configure(&mut camera);
+
+    camera.render(&world, &lights);
+}
@@ -1,3 +1,16 @@
+pub mod bubbles;
+pub mod cloth;
+pub mod dispersion;
 pub mod first;
+pub mod glass;
+pub mod iridescence;
+pub mod layered;
+pub mod lights;
+pub mod metals;
+pub mod principled;
+pub mod sdf;
 pub mod second;
+pub mod smoke;
+pub mod subsurface;
+pub mod terrain;
 pub mod third;
@@ -0,0 +1,140 @@
+use crate::utils::{
+    camera::Camera,
+    color::Color,
+    hittable::{Hittable, HittableList},
+    material::{DiffuseLight, Principled},
+    options::RenderOptions,
+    point3::Point3,
+    sphere::Sphere,
+    texture::{CheckerTexture, SolidColor, TextureParam},
+    vec3::Vec3,
+};
+
+pub fn run(options: &RenderOptions) {
+    // Textures
+    let dark = SolidColor {
+        color: Color::new(0.2, 0.3, 0.1),
+    };
+    let light_squares = SolidColor {
+        color: Color::new(0.9, 0.9, 0.9),
+    };
+    let checker = CheckerTexture {
+        scale: 0.5,
+        even: &dark,
+        odd: &light_squares,
+    };
+    // glossy dark squares, matte light ones
+    let checker_roughness = CheckerTexture {
+        scale: 0.5,
+        even: &SolidColor {
+            color: Color::new(0.1, 0.1, 0.1),
+        },
+        odd: &SolidColor {
+            color: Color::new(0.9, 0.9, 0.9),
+        },
+    };
+
+    // Materials
+    let material_ground = Principled {
+        base_color: TextureParam::Texture(&checker),
+        roughness: TextureParam::Texture(&checker_roughness),
+        ..Default::default()
+    };
+    let material_plastic = Principled {
+        base_color: TextureParam::Constant(Color::new(0.1, 0.2, 0.6)),
+        roughness: TextureParam::scalar(0.3),
+        ..Default::default()
+    };
+    let material_metal = Principled {
+        base_color: TextureParam::Constant(Color::new(0.95, 0.64, 0.54)),
+        metallic: TextureParam::scalar(1.),
+        roughness: TextureParam::scalar(0.35),
+        ..Default::default()
+    };
+    // rough red base under a glossy lacquer
+    let material_paint = Principled {
+        base_color: TextureParam::Constant(Color::new(0.6, 0.05, 0.05)),
+        roughness: TextureParam::scalar(0.6),
+        clearcoat: TextureParam::scalar(1.),
+        ..Default::default()
+    };
+    let material_cloth = Principled {
+        base_color: TextureParam::Constant(Color::new(0.5, 0.3, 0.6)),
+        roughness: TextureParam::scalar(1.),
+        specular: TextureParam::scalar(0.),
+        sheen: TextureParam::scalar(1.),
+        ..Default::default()
+    };
+    let material_glass = Principled {
+        base_color: TextureParam::Constant(Color::new(0.9, 1., 0.95)),
+        roughness: TextureParam::scalar(0.1),
+        transmission: TextureParam::scalar(1.),
+        ..Default::default()
+    };
+    let material_light = DiffuseLight {
+        emit: Color::new(8., 8., 8.),
+    };
+
+    let ground = Sphere {
+        center: Point3::new(0., -100.5, -3.),
+        radius: 100.,
+        material: Some(&material_ground),
+    };
+    let plastic = Sphere {
+        center: Point3::new(-1.65, 0., -3.),
+        radius: 0.5,
+        material: Some(&material_plastic),
+    };
+    let metal = Sphere {
+        center: Point3::new(-0.55, 0., -3.),
+        radius: 0.5,
+        material: Some(&material_metal),
+    };
+    let paint = Sphere {
+        center: Point3::new(0.55, 0., -3.),
+        radius: 0.5,
+        material: Some(&material_paint),
+    };
+    let cloth = Sphere {
+        center: Point3::new(1.65, 0., -3.),
+        radius: 0.5,
+        material: Some(&material_cloth),
+    };
+    let glass = Sphere {
+        center: Point3::new(0., -0.2, -2.),
+        radius: 0.3,
+        material: Some(&material_glass),
+    };
+    let light = Sphere {
+        center: Point3::new(-2., 3., -1.),
+        radius: 0.6,
+        material: Some(&material_light),
+    };
+
+    // World
+    let mut world: HittableList<dyn Hittable> = HittableList::default();
+    world.add(&ground);
+    world.add(&plastic);
+    world.add(&metal);
+    world.add(&paint);
+    world.add(&cloth);
+    world.add(&glass);
+    world.add(&light);
+
+    let mut lights: HittableList<dyn Hittable> = HittableList::default();
+    lights.add(&light);
+
+    let mut camera = Camera::initialize(
+        16. / 9.,
+        400,
+        100,
+        50,
+        50.,
+        Point3::new(0., 0., 0.),
+        Point3::new(0., 0., -3.),
+        Vec3::new(0., 1., 0.),
+    );
+    options.configure(&mut camera);
+
+    camera.render(&world, &lights);
+}
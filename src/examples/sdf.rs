@@ -0,0 +1,82 @@
+use crate::utils::{
+    camera::Camera,
+    color::Color,
+    hittable::{Hittable, HittableList},
+    material::{Dielectric, Lambertian, Metal},
+    options::RenderOptions,
+    point3::Point3,
+    sdf::{Mandelbulb, RoundedBox, SdfObject, SdfSphere, SmoothUnion},
+    sphere::Sphere,
+    vec3::Vec3,
+};
+
+pub fn run(options: &RenderOptions) {
+    // Materials
+    let material_ground = Lambertian {
+        albedo: Color::new(0.8, 0.8, 0.),
+    };
+    let material_box = Dielectric {
+        refraction_index: 1.5,
+        absorption: Color::zero(),
+        dispersion: None,
+        thin_film: None,
+    };
+    let material_blob = Lambertian {
+        albedo: Color::new(0.1, 0.2, 0.5),
+    };
+    let material_bulb = Metal::new(Color::new(0.8, 0.6, 0.2), 0.1);
+
+    // Shapes
+    let rounded_box = RoundedBox {
+        center: Point3::new(-1.2, 0., -3.),
+        half_extents: Vec3::new(0.4, 0.4, 0.4),
+        radius: 0.1,
+    };
+    let blob_top = SdfSphere {
+        center: Point3::new(0., 0.15, -3.2),
+        radius: 0.3,
+    };
+    let blob_bottom = SdfSphere {
+        center: Point3::new(0., -0.25, -3.2),
+        radius: 0.25,
+    };
+    let blob = SmoothUnion {
+        a: &blob_top,
+        b: &blob_bottom,
+        k: 0.2,
+    };
+    let bulb = Mandelbulb::new(Point3::new(1.2, 0., -3.), 0.45);
+
+    let ground = Sphere {
+        center: Point3::new(0., -100.5, -3.),
+        radius: 100.0,
+        material: Some(&material_ground),
+    };
+    let object1 = SdfObject::new(&rounded_box, &material_box);
+    let object2 = SdfObject::new(&blob, &material_blob);
+    let object3 = SdfObject::new(&bulb, &material_bulb);
+
+    // World
+    let mut world: HittableList<dyn Hittable> = HittableList::default();
+    world.add(&ground);
+    world.add(&object1);
+    world.add(&object2);
+    world.add(&object3);
+
+    let mut camera = Camera::initialize(
+        16. / 9.,
+        400,
+        100,
+        50,
+        45.,
+        Point3::new(0., 0., 0.),
+        Point3::new(0., -0.1, -3.),
+        Vec3::new(0., 1., 0.),
+    );
+    options.configure(&mut camera);
+
+    // no light sources to sample directly
+    let lights: HittableList<dyn Hittable> = HittableList::default();
+
+    camera.render(&world, &lights);
+}
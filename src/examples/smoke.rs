@@ -0,0 +1,72 @@
+use crate::utils::{
+    aabb::Aabb,
+    camera::Camera,
+    color::Color,
+    hittable::{Hittable, HittableList},
+    material::{Isotropic, Lambertian},
+    options::RenderOptions,
+    point3::Point3,
+    sphere::Sphere,
+    vec3::Vec3,
+    volume::HeterogeneousVolume,
+    voxel_grid::VoxelGrid,
+};
+
+pub fn run(options: &RenderOptions) {
+    // Density and temperature grids for a rising plume, generated procedurally
+    // here; VoxelGrid::load_vol / load_raw read simulation output instead
+    let bounds = Aabb::new(Point3::new(-0.6, -0.5, -1.6), Point3::new(0.6, 1.3, -0.4));
+    let smoke = VoxelGrid::from_fn(48, 72, 48, bounds, |p| {
+        let height = p.y + 0.5;
+        let radius = 0.15 + 0.2 * height;
+        let swirl = 0.08 * f64::sin(8. * p.y) * f64::cos(5. * p.z);
+        let r = ((p.x - swirl).powi(2) + (p.z + 1.).powi(2)).sqrt();
+        let wisps = 0.5 + 0.5 * f64::sin(12. * p.x + 9. * p.y) * f64::sin(11. * p.z - 7. * p.y);
+        f64::max(0., 1. - r / radius) * wisps * f64::max(0., 1. - height / 1.8)
+    })
+    .unwrap();
+    let temperature = VoxelGrid::from_fn(24, 36, 24, bounds, |p| {
+        let r = (p.x.powi(2) + (p.z + 1.).powi(2)).sqrt();
+        f64::max(0., 1. - r / 0.15) * f64::max(0., 1. - (p.y + 0.5) / 0.5)
+    })
+    .unwrap();
+
+    // Materials
+    let material_ground = Lambertian {
+        albedo: Color::new(0.5, 0.5, 0.5),
+    };
+    let material_smoke = Isotropic::emissive(
+        Color::new(0.7, 0.7, 0.7),
+        Color::new(27., 10., 2.),
+        &temperature,
+    );
+
+    let ground = Sphere {
+        center: Point3::new(0., -100.5, -1.),
+        radius: 100.0,
+        material: Some(&material_ground),
+    };
+    let plume = HeterogeneousVolume::new(&smoke, 12., &material_smoke);
+
+    // World
+    let mut world: HittableList<dyn Hittable> = HittableList::default();
+    world.add(&ground);
+    world.add(&plume);
+
+    let mut camera = Camera::initialize(
+        16. / 9.,
+        400,
+        100,
+        50,
+        40.,
+        Point3::new(0., 0.5, 2.),
+        Point3::new(0., 0.4, -1.),
+        Vec3::new(0., 1., 0.),
+    );
+    options.configure(&mut camera);
+
+    // no light sources to sample directly
+    let lights: HittableList<dyn Hittable> = HittableList::default();
+
+    camera.render(&world, &lights);
+}
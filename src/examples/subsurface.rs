@@ -0,0 +1,115 @@
+use crate::utils::{
+    camera::Camera,
+    color::Color,
+    hittable::{Hittable, HittableList},
+    material::{Dielectric, DiffuseLight, Lambertian, RoughDielectric, SubsurfaceMedium},
+    options::RenderOptions,
+    point3::Point3,
+    sphere::Sphere,
+    vec3::Vec3,
+    volume::Subsurface,
+};
+
+pub fn run(options: &RenderOptions) {
+    // Materials
+    let material_ground = Lambertian {
+        albedo: Color::new(0.5, 0.5, 0.5),
+    };
+    let material_interface = Dielectric {
+        refraction_index: 1.4,
+        absorption: Color::zero(),
+        dispersion: None,
+        thin_film: None,
+    };
+    let material_rough_interface = RoughDielectric::new(1.4, 0.4);
+    // bright and dense, so light doesn't get far before turning back
+    let material_marble = SubsurfaceMedium::from_albedo(Color::new(0.99, 0.98, 0.96), 0.05, 0.);
+    // skin's red travels further than its blue (Jensen et al.'s measured
+    // coefficients, rescaled to this scene)
+    let material_skin = SubsurfaceMedium {
+        sigma_s: Color::new(0.74, 0.88, 1.01) * 8.,
+        sigma_a: Color::new(0.032, 0.17, 0.48) * 8.,
+        anisotropy: 0.,
+    };
+    let material_jade = SubsurfaceMedium::from_albedo(Color::new(0.6, 0.95, 0.7), 0.1, 0.3);
+    // thin and forward scattering, so it glows where it is lit from behind
+    let material_wax = SubsurfaceMedium::from_albedo(Color::new(0.97, 0.85, 0.6), 0.15, 0.7);
+    let material_light = DiffuseLight {
+        emit: Color::new(4., 4., 4.),
+    };
+
+    let ground = Sphere {
+        center: Point3::new(0., -100.5, -3.),
+        radius: 100.,
+        material: Some(&material_ground),
+    };
+    let marble_boundary = Sphere {
+        center: Point3::new(-1.65, 0., -3.),
+        radius: 0.5,
+        material: Some(&material_interface),
+    };
+    let skin_boundary = Sphere {
+        center: Point3::new(-0.55, 0., -3.),
+        radius: 0.5,
+        material: Some(&material_rough_interface),
+    };
+    let jade_boundary = Sphere {
+        center: Point3::new(0.55, 0., -3.),
+        radius: 0.5,
+        material: Some(&material_interface),
+    };
+    let wax_boundary = Sphere {
+        center: Point3::new(1.65, 0., -3.),
+        radius: 0.5,
+        material: Some(&material_rough_interface),
+    };
+    let marble = Subsurface {
+        boundary: &marble_boundary,
+        medium: &material_marble,
+    };
+    let skin = Subsurface {
+        boundary: &skin_boundary,
+        medium: &material_skin,
+    };
+    let jade = Subsurface {
+        boundary: &jade_boundary,
+        medium: &material_jade,
+    };
+    let wax = Subsurface {
+        boundary: &wax_boundary,
+        medium: &material_wax,
+    };
+    // a big, soft light, since the walks can only reach it by chance
+    let light = Sphere {
+        center: Point3::new(-3., 4., -1.),
+        radius: 1.5,
+        material: Some(&material_light),
+    };
+
+    // World
+    let mut world: HittableList<dyn Hittable> = HittableList::default();
+    world.add(&ground);
+    world.add(&marble);
+    world.add(&skin);
+    world.add(&jade);
+    world.add(&wax);
+    world.add(&light);
+
+    let mut lights: HittableList<dyn Hittable> = HittableList::default();
+    lights.add(&light);
+
+    // random walks take many bounces
+    let mut camera = Camera::initialize(
+        16. / 9.,
+        400,
+        100,
+        256,
+        50.,
+        Point3::new(0., 0., 0.),
+        Point3::new(0., 0., -3.),
+        Vec3::new(0., 1., 0.),
+    );
+    options.configure(&mut camera);
+
+    camera.render(&world, &lights);
+}
@@ -0,0 +1,71 @@
+use crate::utils::{
+    aabb::Aabb,
+    camera::Camera,
+    color::Color,
+    heightfield::Heightfield,
+    hittable::{Hittable, HittableList},
+    material::{Dielectric, Lambertian},
+    options::RenderOptions,
+    point3::Point3,
+    sphere::Sphere,
+    vec3::Vec3,
+};
+
+pub fn run(options: &RenderOptions) {
+    // Materials
+    let material_terrain = Lambertian {
+        albedo: Color::new(0.45, 0.4, 0.3),
+    };
+    let material_glass = Dielectric {
+        refraction_index: 1.5,
+        absorption: Color::zero(),
+        dispersion: None,
+        thin_film: None,
+    };
+
+    // Rolling hills from a few octaves of sine waves; Heightfield::load_pgm
+    // and Heightfield::load_raw read real elevation data instead
+    let bounds = Aabb::new(Point3::new(-4., -1.5, -9.), Point3::new(4., -0.3, -1.));
+    let terrain = Heightfield::from_fn(256, 256, bounds, &material_terrain, |u, v| {
+        let mut height = 0.;
+        let mut amplitude = 0.5;
+        let mut frequency = 3.;
+        for _ in 0..5 {
+            height += amplitude
+                * (0.5
+                    + 0.25 * f64::sin(frequency * u * 6.1 + 1.3 * v)
+                    + 0.25 * f64::cos(frequency * v * 5.3 - 2.1 * u));
+            amplitude *= 0.5;
+            frequency *= 2.1;
+        }
+        height
+    })
+    .unwrap();
+    let sphere = Sphere {
+        center: Point3::new(0.6, -0.4, -3.5),
+        radius: 0.35,
+        material: Some(&material_glass),
+    };
+
+    // World
+    let mut world: HittableList<dyn Hittable> = HittableList::default();
+    world.add(&terrain);
+    world.add(&sphere);
+
+    let mut camera = Camera::initialize(
+        16. / 9.,
+        400,
+        100,
+        50,
+        60.,
+        Point3::new(0., 0., 0.),
+        Point3::new(0., -0.5, -3.),
+        Vec3::new(0., 1., 0.),
+    );
+    options.configure(&mut camera);
+
+    // no light sources to sample directly
+    let lights: HittableList<dyn Hittable> = HittableList::default();
+
+    camera.render(&world, &lights);
+}
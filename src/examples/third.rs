@@ -3,12 +3,13 @@ use crate::utils::{
     color::Color,
     hittable::HittableList,
     material::{Dielectric, Lambertian, Metal},
+    options::RenderOptions,
     point3::Point3,
     sphere::Sphere,
     vec3::Vec3,
 };
 
-pub fn run() {
+pub fn run(options: &RenderOptions) {
     // Materials
     let material_ground = Lambertian {
         albedo: Color::new(0.8, 0.8, 0.),
@@ -18,9 +19,15 @@ pub fn run() {
     };
     let material_left = Dielectric {
         refraction_index: 1.5,
+        absorption: Color::zero(),
+        dispersion: None,
+        thin_film: None,
     };
     let material_bubble = Dielectric {
         refraction_index: 1. / 1.5,
+        absorption: Color::zero(),
+        dispersion: None,
+        thin_film: None,
     };
     let material_right = Metal::new(Color::new(0.8, 0.6, 0.2), 1.);
 
@@ -58,7 +65,7 @@ pub fn run() {
     world.add(&sphere4);
     world.add(&sphere5);
 
-    let camera = Camera::initialize(
+    let mut camera = Camera::initialize(
         16. / 9.,
         400,
         100,
@@ -68,6 +75,10 @@ pub fn run() {
         Point3::new(0., 0., -1.),
         Vec3::new(0., 1., 0.),
     );
+    options.configure(&mut camera);
 
-    camera.render(&world);
+    // no light sources to sample directly
+    let lights: HittableList<Sphere> = HittableList::default();
+
+    camera.render(&world, &lights);
 }
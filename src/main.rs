@@ -1,9 +1,21 @@
-use clap::{Arg, Command};
+use clap::{Arg, ArgAction, Command};
 
 mod examples;
 pub mod utils;
 
-use examples::{first, second, third};
+use examples::{
+    bubbles, cloth, dispersion, first, glass, iridescence, layered, lights, metals, principled,
+    sdf, second, smoke, subsurface, terrain, third,
+};
+use utils::{
+    aov::Aov,
+    color::ToneMapping,
+    debug_integrator::DebugMode,
+    denoiser::Denoiser,
+    filter::FilterKind,
+    options::{IntegratorKind, RenderOptions},
+    sampler::SamplerKind,
+};
 
 fn main() {
     let matches = Command::new("Ray Tracing Examples")
@@ -17,9 +29,189 @@ fn main() {
                 .help("Specifies which example to run")
                 .required(true),
         )
+        .arg(
+            Arg::new("integrator")
+                .short('i')
+                .long("integrator")
+                .help("Light transport algorithm to render with")
+                .value_parser(IntegratorKind::NAMES),
+        )
+        .arg(
+            Arg::new("ao-samples")
+                .long("ao-samples")
+                .help("Occlusion rays per camera sample for the ao integrator")
+                .value_parser(clap::value_parser!(u32))
+                .default_value("16"),
+        )
+        .arg(
+            Arg::new("ao-distance")
+                .long("ao-distance")
+                .help("Distance beyond which geometry no longer occludes for the ao integrator")
+                .value_parser(clap::value_parser!(f64))
+                .default_value("1.0"),
+        )
+        .arg(
+            Arg::new("spectral")
+                .long("spectral")
+                .help("Renders with sampled wavelengths instead of RGB so dispersive glass splits light; needs the path integrator")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("seed")
+                .long("seed")
+                .help("Seed for all random sampling; equal seeds give identical images")
+                .value_parser(clap::value_parser!(u64))
+                .default_value("0"),
+        )
+        .arg(
+            Arg::new("sampler")
+                .long("sampler")
+                .help("How sample points are distributed within pixels and along paths")
+                .value_parser(SamplerKind::NAMES)
+                .default_value("independent"),
+        )
+        .arg(
+            Arg::new("noise-threshold")
+                .long("noise-threshold")
+                .help("Enables adaptive sampling: pixels stop once their relative error is below this")
+                .value_parser(clap::value_parser!(f64)),
+        )
+        .arg(
+            Arg::new("min-samples")
+                .long("min-samples")
+                .help("Samples every pixel takes before adaptive sampling may stop it")
+                .value_parser(clap::value_parser!(u32))
+                .default_value("16"),
+        )
+        .arg(
+            Arg::new("max-samples")
+                .long("max-samples")
+                .help("Samples per pixel cap, replacing the example's own sample count")
+                .value_parser(clap::value_parser!(u32)),
+        )
+        .arg(
+            Arg::new("spp-map")
+                .long("spp-map")
+                .help("Writes a grayscale PPM of the samples taken per pixel to this path"),
+        )
+        .arg(
+            Arg::new("filter")
+                .long("filter")
+                .help("Pixel reconstruction filter")
+                .value_parser(FilterKind::NAMES)
+                .default_value("box"),
+        )
+        .arg(
+            Arg::new("filter-radius")
+                .long("filter-radius")
+                .help("Filter radius in pixels; defaults to the usual radius for the filter")
+                .value_parser(clap::value_parser!(f64)),
+        )
+        .arg(
+            Arg::new("exposure")
+                .long("exposure")
+                .help("Exposure adjustment in stops applied before tone mapping")
+                .value_parser(clap::value_parser!(f64))
+                .allow_negative_numbers(true)
+                .default_value("0"),
+        )
+        .arg(
+            Arg::new("tone-mapping")
+                .long("tone-mapping")
+                .help("Operator that maps scene radiance into the display range")
+                .value_parser(ToneMapping::NAMES)
+                .default_value("none"),
+        )
+        .arg(
+            Arg::new("white-point")
+                .long("white-point")
+                .help("Luminance mapped to white by extended-reinhard")
+                .value_parser(clap::value_parser!(f64))
+                .default_value("4.0"),
+        )
+        .arg(
+            Arg::new("dither")
+                .long("dither")
+                .help("Dithers before quantizing to 8 bits to avoid banding")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("aov")
+                .long("aov")
+                .help("Extra buffers to write next to the image, comma separated")
+                .value_parser(Aov::NAMES)
+                .value_delimiter(',')
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("aov-prefix")
+                .long("aov-prefix")
+                .help("Path prefix for AOV files, written as <prefix>_<aov>.pfm")
+                .default_value("aov"),
+        )
+        .arg(
+            Arg::new("denoise")
+                .long("denoise")
+                .help("Denoises the image, guided by albedo and normals")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("denoise-iterations")
+                .long("denoise-iterations")
+                .help("Denoiser passes; each one reaches twice as far as the last")
+                .value_parser(clap::value_parser!(u32))
+                .default_value("5"),
+        )
+        .arg(
+            Arg::new("denoise-strength")
+                .long("denoise-strength")
+                .help("Luminance differences, in standard deviations of noise, the denoiser blends across")
+                .value_parser(clap::value_parser!(f64))
+                .default_value("4.0"),
+        )
+        .arg(
+            Arg::new("debug")
+                .long("debug")
+                .help("Renders a debug view of the first hit instead of shading")
+                .value_parser(DebugMode::NAMES),
+        )
         .get_matches();
 
     let example = matches.get_one::<String>("example").unwrap().as_str();
+    let options = RenderOptions {
+        integrator: matches
+            .get_one::<String>("integrator")
+            .map(|name| IntegratorKind::from_name(name).unwrap()),
+        ao_samples: *matches.get_one::<u32>("ao-samples").unwrap(),
+        ao_distance: *matches.get_one::<f64>("ao-distance").unwrap(),
+        spectral: matches.get_flag("spectral"),
+        debug_mode: matches
+            .get_one::<String>("debug")
+            .map(|name| DebugMode::from_name(name).unwrap()),
+        seed: *matches.get_one::<u64>("seed").unwrap(),
+        sampler: SamplerKind::from_name(matches.get_one::<String>("sampler").unwrap()).unwrap(),
+        noise_threshold: matches.get_one::<f64>("noise-threshold").copied(),
+        min_samples: *matches.get_one::<u32>("min-samples").unwrap(),
+        max_samples: matches.get_one::<u32>("max-samples").copied(),
+        spp_map_path: matches.get_one::<String>("spp-map").cloned(),
+        filter: FilterKind::from_name(matches.get_one::<String>("filter").unwrap()).unwrap(),
+        filter_radius: matches.get_one::<f64>("filter-radius").copied(),
+        exposure: *matches.get_one::<f64>("exposure").unwrap(),
+        tone_mapping: ToneMapping::from_name(matches.get_one::<String>("tone-mapping").unwrap())
+            .unwrap(),
+        white_point: *matches.get_one::<f64>("white-point").unwrap(),
+        dither: matches.get_flag("dither"),
+        aovs: matches
+            .get_many::<String>("aov")
+            .map(|names| names.map(|name| Aov::from_name(name).unwrap()).collect())
+            .unwrap_or_default(),
+        aov_prefix: matches.get_one::<String>("aov-prefix").unwrap().clone(),
+        denoiser: matches.get_flag("denoise").then(|| Denoiser {
+            iterations: *matches.get_one::<u32>("denoise-iterations").unwrap(),
+            luminance_sigma: *matches.get_one::<f64>("denoise-strength").unwrap(),
+            ..Denoiser::default()
+        }),
+    };
 
     match example {
         "first" => {
@@ -29,11 +221,52 @@ fn main() {
             second::run();
         }
         "third" => {
-            third::run();
+            third::run(&options);
+        }
+        "smoke" => {
+            smoke::run(&options);
+        }
+        "sdf" => {
+            sdf::run(&options);
+        }
+        "terrain" => {
+            terrain::run(&options);
+        }
+        "lights" => {
+            lights::run(&options);
+        }
+        "metals" => {
+            metals::run(&options);
+        }
+        "glass" => {
+            glass::run(&options);
+        }
+        "principled" => {
+            principled::run(&options);
+        }
+        "layered" => {
+            layered::run(&options);
+        }
+        "cloth" => {
+            cloth::run(&options);
+        }
+        "subsurface" => {
+            subsurface::run(&options);
+        }
+        "dispersion" => {
+            dispersion::run(&options);
+        }
+        "iridescence" => {
+            iridescence::run(&options);
+        }
+        "bubbles" => {
+            bubbles::run(&options);
         }
         _ => {
             println!("Unknown example: {}", example);
-            println!("Available examples are: basic, lighting, shadows");
+            println!(
+                "Available examples are: first, second, third, smoke, sdf, terrain, lights, metals, glass, principled, layered, cloth, subsurface, dispersion, iridescence, bubbles"
+            );
         }
     }
 }
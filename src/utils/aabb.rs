@@ -0,0 +1,71 @@
+use super::interval::Interval;
+use super::point3::Point3;
+use super::ray::Ray;
+
+#[derive(Copy, Clone, Default)]
+pub struct Aabb {
+    pub min: Point3,
+    pub max: Point3,
+}
+
+impl Aabb {
+    pub fn new(min: Point3, max: Point3) -> Aabb {
+        Aabb { min, max }
+    }
+
+    pub fn surrounding(box0: &Aabb, box1: &Aabb) -> Aabb {
+        Aabb {
+            min: Point3::new(
+                f64::min(box0.min.x, box1.min.x),
+                f64::min(box0.min.y, box1.min.y),
+                f64::min(box0.min.z, box1.min.z),
+            ),
+            max: Point3::new(
+                f64::max(box0.max.x, box1.max.x),
+                f64::max(box0.max.y, box1.max.y),
+                f64::max(box0.max.z, box1.max.z),
+            ),
+        }
+    }
+
+    pub fn contains(&self, p: &Point3) -> bool {
+        self.min.x <= p.x
+            && p.x <= self.max.x
+            && self.min.y <= p.y
+            && p.y <= self.max.y
+            && self.min.z <= p.z
+            && p.z <= self.max.z
+    }
+
+    // slab test: returns the part of ray_t that lies inside the box
+    pub fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<Interval> {
+        let mut t_min = ray_t.min;
+        let mut t_max = ray_t.max;
+
+        let axes = [
+            (ray.origin.x, ray.direction.x, self.min.x, self.max.x),
+            (ray.origin.y, ray.direction.y, self.min.y, self.max.y),
+            (ray.origin.z, ray.direction.z, self.min.z, self.max.z),
+        ];
+        for (origin, direction, min, max) in axes {
+            let inv_d = 1. / direction;
+            let mut t0 = (min - origin) * inv_d;
+            let mut t1 = (max - origin) * inv_d;
+            if inv_d < 0. {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            // NaN (origin on a slab with a parallel ray) must not shrink the interval
+            if t0 > t_min {
+                t_min = t0;
+            }
+            if t1 < t_max {
+                t_max = t1;
+            }
+            if t_max <= t_min {
+                return None;
+            }
+        }
+
+        Some(Interval::bounded_interval(t_min, t_max))
+    }
+}
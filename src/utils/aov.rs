@@ -0,0 +1,208 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+};
+
+use super::{color::Color, hittable::HitRecord, integrator::LightSplit, ray::Ray};
+
+// Extra per-pixel buffers rendered alongside the image for compositing.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Aov {
+    Albedo,
+    // world space shading normal
+    Normal,
+    // distance from the camera
+    Depth,
+    // world space hit point
+    Position,
+    // index of the object hit plus one, so 0 means nothing was hit
+    ObjectId,
+    // light reaching the first non-specular surface straight from an emitter
+    Direct,
+    // light that reflected off more than one non-specular surface
+    Indirect,
+    // emitters and background seen directly, or through mirrors and glass
+    Emission,
+}
+
+impl Aov {
+    pub const NAMES: [&'static str; 8] = [
+        "albedo",
+        "normal",
+        "depth",
+        "position",
+        "object-id",
+        "direct",
+        "indirect",
+        "emission",
+    ];
+
+    pub fn from_name(name: &str) -> Option<Aov> {
+        match name {
+            "albedo" => Some(Aov::Albedo),
+            "normal" => Some(Aov::Normal),
+            "depth" => Some(Aov::Depth),
+            "position" => Some(Aov::Position),
+            "object-id" => Some(Aov::ObjectId),
+            "direct" => Some(Aov::Direct),
+            "indirect" => Some(Aov::Indirect),
+            "emission" => Some(Aov::Emission),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Aov::Albedo => "albedo",
+            Aov::Normal => "normal",
+            Aov::Depth => "depth",
+            Aov::Position => "position",
+            Aov::ObjectId => "object-id",
+            Aov::Direct => "direct",
+            Aov::Indirect => "indirect",
+            Aov::Emission => "emission",
+        }
+    }
+
+    // whether the integrator has to split its radiance to fill this one
+    pub fn needs_light_split(&self) -> bool {
+        matches!(self, Aov::Direct | Aov::Indirect | Aov::Emission)
+    }
+
+    // this AOV's value for one camera sample; misses are all zero
+    fn value(&self, ray: &Ray, first_hit: Option<&HitRecord>, split: &LightSplit) -> Color {
+        match self {
+            Aov::Direct => return split.direct,
+            Aov::Indirect => return split.indirect,
+            Aov::Emission => return split.emission,
+            _ => {}
+        }
+
+        let hit_record = match first_hit {
+            Some(hit_record) => hit_record,
+            None => return Color::zero(),
+        };
+        match self {
+            Aov::Albedo => hit_record.material.unwrap().albedo(hit_record),
+            Aov::Normal => hit_record.normal,
+            Aov::Depth => {
+                let distance = hit_record.t * ray.direction.norm();
+                Color::new(distance, distance, distance)
+            }
+            Aov::Position => hit_record.p,
+            Aov::ObjectId => {
+                let id = (hit_record.object_id + 1) as f64;
+                Color::new(id, id, id)
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+// Filter-weighted sums of each requested AOV over a pixel's samples, the
+// same way the image itself is accumulated, for the whole image.
+pub struct AovBuffers {
+    pub aovs: Vec<Aov>,
+    pub width: u32,
+    pub height: u32,
+    // one row-major buffer per entry of `aovs`
+    pub buffers: Vec<Vec<Color>>,
+    // object ids can't be averaged, so that buffer keeps the id of the
+    // sample with the largest weight
+    best_weights: Vec<f64>,
+}
+
+impl AovBuffers {
+    pub fn new(aovs: Vec<Aov>, width: u32, height: u32) -> AovBuffers {
+        let pixels = (width * height) as usize;
+        AovBuffers {
+            buffers: vec![vec![Color::zero(); pixels]; aovs.len()],
+            aovs,
+            width,
+            height,
+            best_weights: vec![f64::NEG_INFINITY; pixels],
+        }
+    }
+
+    pub fn needs_light_split(&self) -> bool {
+        self.aovs.iter().any(|aov| aov.needs_light_split())
+    }
+
+    pub fn add_sample(
+        &mut self,
+        i: u32,
+        j: u32,
+        ray: &Ray,
+        first_hit: Option<&HitRecord>,
+        split: &LightSplit,
+        weight: f64,
+    ) {
+        let pixel = (j * self.width + i) as usize;
+        let take_id = weight > self.best_weights[pixel];
+        if take_id {
+            self.best_weights[pixel] = weight;
+        }
+
+        for (aov, buffer) in self.aovs.iter().zip(self.buffers.iter_mut()) {
+            let value = aov.value(ray, first_hit, split);
+            if *aov == Aov::ObjectId {
+                if take_id {
+                    buffer[pixel] = value;
+                }
+            } else {
+                buffer[pixel] += value * weight;
+            }
+        }
+    }
+
+    // turns the pixel's sums into weighted means
+    pub fn finish_pixel(&mut self, i: u32, j: u32, weight_sum: f64) {
+        let pixel = (j * self.width + i) as usize;
+        for (aov, buffer) in self.aovs.iter().zip(self.buffers.iter_mut()) {
+            if *aov == Aov::ObjectId {
+                continue;
+            }
+            buffer[pixel] = if weight_sum > 0. {
+                buffer[pixel] / weight_sum
+            } else {
+                Color::zero()
+            };
+        }
+    }
+
+    pub fn get(&self, aov: Aov) -> Option<&[Color]> {
+        self.aovs
+            .iter()
+            .position(|&a| a == aov)
+            .map(|index| self.buffers[index].as_slice())
+    }
+
+    // writes the buffers for `only` to `<prefix>_<aov>.pfm`
+    pub fn write(&self, prefix: &str, only: &[Aov]) -> io::Result<()> {
+        for aov in only {
+            if let Some(buffer) = self.get(*aov) {
+                let path = format!("{prefix}_{}.pfm", aov.name());
+                write_pfm(&path, self.width, self.height, buffer)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Portable float map: an uncompressed 32-bit float RGB image, so depths and
+// positions keep their full range. Rows are stored bottom to top.
+pub fn write_pfm(path: &str, width: u32, height: u32, pixels: &[Color]) -> io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    // a negative scale marks the data as little endian
+    write!(file, "PF\n{width} {height}\n-1.0\n")?;
+
+    for j in (0..height).rev() {
+        for i in 0..width {
+            let pixel = pixels[(j * width + i) as usize];
+            for channel in [pixel.x, pixel.y, pixel.z] {
+                file.write_all(&(channel as f32).to_le_bytes())?;
+            }
+        }
+    }
+    file.flush()
+}
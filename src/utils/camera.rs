@@ -1,25 +1,55 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+};
+
 use super::{
-    color::{write_color, Color},
+    aov::{Aov, AovBuffers},
+    color::{luminance, Color, ColorPipeline},
+    denoiser::Denoiser,
+    filter::Filter,
     hittable::{HitRecord, Hittable},
-    interval::Interval,
+    integrator::{Integrator, IterativePathTracer, Scene},
     point3::Point3,
     ray::Ray,
+    sampler::{Sampler, SamplerKind},
     vec3::Vec3,
 };
 
-use rand::Rng;
-
 pub struct Camera {
     pub aspect_ratio: f64,
     image_width: u32,
-    samples_per_pixel: u32,
+    // the most samples any pixel gets
+    pub samples_per_pixel: u32,
+    // with a noise threshold set, pixels stop sampling once their estimated
+    // error falls below it, though never before `min_samples_per_pixel`
+    pub min_samples_per_pixel: u32,
+    pub noise_threshold: Option<f64>,
+    // where to write a grayscale image of the samples each pixel took
+    pub spp_map_path: Option<String>,
     pub max_depth: u32,
     image_height: u32,
     pub vfov: f64,
     pub lookfrom: Point3,
     pub lookat: Point3,
     pub vup: Vec3,
-    pixel_samples_scale: f64,
+    // solid color behind the scene; None keeps the sky gradient
+    pub background: Option<Color>,
+    // light transport used for every sample, a path tracer by default
+    pub integrator: Box<dyn Integrator>,
+    // all randomness derives from this, so equal seeds give equal images
+    pub seed: u64,
+    // how sample points are spread over the pixel and path dimensions
+    pub sampler: SamplerKind,
+    // how samples around each pixel center are weighted into it
+    pub filter: Filter,
+    // exposure and tone mapping from the linear image to the output file
+    pub color_pipeline: ColorPipeline,
+    // extra buffers written next to the image as `<aov_prefix>_<name>.pfm`
+    pub aovs: Vec<Aov>,
+    pub aov_prefix: String,
+    // filters the finished image using the albedo and normal AOVs
+    pub denoiser: Option<Denoiser>,
     center: Point3,
     pixel00_loc: Point3,
     pixel_delta_u: Vec3,
@@ -65,7 +95,6 @@ impl Camera {
         // Pixel deltas
         let pixel_delta_u = viewport_u / image_width as f64;
         let pixel_delta_v = viewport_v / image_height as f64;
-        let pixel_samples_scale = 1. / samples_per_pixel as f64;
 
         // Upper left pixel
         let viewport_upper_left = center - w * focal_length - viewport_u / 2. - viewport_v / 2.;
@@ -75,13 +104,24 @@ impl Camera {
             aspect_ratio,
             image_width,
             samples_per_pixel,
+            min_samples_per_pixel: 16,
+            noise_threshold: None,
+            spp_map_path: None,
             max_depth,
             vfov,
             lookfrom,
             lookat,
             vup,
+            background: None,
+            integrator: Box::new(IterativePathTracer::default()),
+            seed: 0,
+            sampler: SamplerKind::Independent,
+            filter: Filter::default(),
+            color_pipeline: ColorPipeline::default(),
+            aovs: Vec::new(),
+            aov_prefix: String::from("aov"),
+            denoiser: None,
             image_height,
-            pixel_samples_scale,
             center,
             pixel00_loc,
             pixel_delta_u,
@@ -89,98 +129,184 @@ impl Camera {
         }
     }
 
-    pub fn ray_color(ray: &Ray, depth: u32, world: &dyn Hittable) -> Color {
-        if depth <= 0 {
-            return Color::zero();
-        }
-
-        let mut hit_record = HitRecord::default();
-        let interval = Interval {
-            min: 0.001,
-            max: f64::INFINITY,
-        };
-        if world.hit(ray, interval, &mut hit_record) {
-            let mut scattered = Ray::default();
-            let mut attenuation = Color::default();
-
-            if hit_record.material.unwrap().scatter(
-                ray,
-                &hit_record,
-                &mut attenuation,
-                &mut scattered,
-            ) {
-                return Self::ray_color(&scattered, depth - 1, world) * attenuation;
-            } else {
-                return Color::zero();
-            }
-        }
-
-        let unit_direction = Vec3::unit_vector(&ray.direction);
-        let a = unit_direction.y * 0.5 + 1.;
-        return Color {
-            x: 1.,
-            y: 1.,
-            z: 1.,
-        } * (1. - a)
-            + Color {
-                x: 0.5,
-                y: 0.7,
-                z: 1.0,
-            } * a;
-    }
-
-    fn get_ray(&self, i: u32, j: u32) -> Ray {
-        let offset = Self::sample_square();
+    // a camera ray through pixel (i, j) and the filter weight of its sample
+    fn get_ray(&self, i: u32, j: u32, sampler: &mut dyn Sampler) -> (Ray, f64) {
+        let (offset_x, offset_y, weight) = self.filter.sample(sampler.get_2d());
+        // time and lens dimensions; drawn even though this camera has neither
+        // motion blur nor defocus so that bounce dimensions always start at 5
+        sampler.get_1d();
+        sampler.get_2d();
         let pixel_sample = self.pixel00_loc
-            + (self.pixel_delta_u * (i as f64 + offset.x))
-            + (self.pixel_delta_v * (j as f64 + offset.y));
+            + (self.pixel_delta_u * (i as f64 + offset_x))
+            + (self.pixel_delta_v * (j as f64 + offset_y));
 
         let ray_origin = self.center;
         let ray_direction = pixel_sample - ray_origin;
 
-        Ray {
-            origin: ray_origin,
-            direction: ray_direction,
-        }
-    }
-
-    fn sample_square() -> Vec3 {
-        let mut rng = rand::thread_rng();
-        let random_x = rng.gen::<f64>();
-        let random_y = rng.gen::<f64>();
-
-        Vec3 {
-            x: random_x - 0.5,
-            y: random_y - 0.5,
-            z: 0.,
-        }
+        let ray = Ray::new(ray_origin, ray_direction);
+        (ray, weight)
     }
 
     fn degrees_to_radians(degrees: f64) -> f64 {
         degrees * std::f64::consts::PI / 180.
     }
 
-    pub fn render(&self, world: &dyn Hittable) {
-        print!(
-            "P3\n{image_width} {image_height}\n255\n",
-            image_width = self.image_width,
-            image_height = self.image_height
-        );
+    pub fn render<'a>(&self, world: &dyn Hittable<'a>, lights: &dyn Hittable<'a>) {
+        let scene = Scene {
+            world,
+            lights,
+            background: self.background,
+            max_depth: self.max_depth,
+        };
+        let mut sampler = self.sampler.create(self.seed, self.samples_per_pixel);
 
+        let mut framebuffer = Vec::with_capacity((self.image_width * self.image_height) as usize);
+        let mut spp_map = Vec::with_capacity((self.image_width * self.image_height) as usize);
+        let mut variance = Vec::with_capacity((self.image_width * self.image_height) as usize);
+        let mut aovs = self.aovs.clone();
+        if self.denoiser.is_some() {
+            for guide in [Aov::Albedo, Aov::Normal] {
+                if !aovs.contains(&guide) {
+                    aovs.push(guide);
+                }
+            }
+        }
+        let mut aov_buffers = AovBuffers::new(aovs, self.image_width, self.image_height);
+        let needs_light_split = aov_buffers.needs_light_split();
+        let mut missing_light_split = false;
         for j in 0..self.image_height {
             for i in 0..self.image_width {
-                let mut pixel_color = Color {
-                    x: 0.,
-                    y: 0.,
-                    z: 0.,
-                };
-                for _ in 0..self.samples_per_pixel {
-                    let ray = self.get_ray(i, j);
-                    pixel_color += Self::ray_color(&ray, self.max_depth, world);
+                let mut pixel_color = Color::zero();
+                let mut weight_sum = 0.;
+                // running mean and sum of squared deviations of luminance
+                let mut mean = 0.;
+                let mut squared_deviations = 0.;
+                let mut samples = 0;
+                while samples < self.samples_per_pixel {
+                    sampler.start_pixel_sample(i, j, samples);
+                    let (ray, weight) = self.get_ray(i, j, sampler.as_mut());
+                    let split = if needs_light_split {
+                        self.integrator.li_split(&ray, &scene, sampler.as_mut())
+                    } else {
+                        None
+                    };
+                    missing_light_split |= needs_light_split && split.is_none();
+                    let sample_color = match &split {
+                        Some(split) => split.total(),
+                        None => self.integrator.li(&ray, &scene, sampler.as_mut()),
+                    };
+                    if !aov_buffers.aovs.is_empty() {
+                        let mut first_hit = HitRecord::default();
+                        let first_hit = scene.hit(&ray, &mut first_hit).then_some(&first_hit);
+                        let split = split.unwrap_or_default();
+                        aov_buffers.add_sample(i, j, &ray, first_hit, &split, weight);
+                    }
+                    pixel_color += sample_color * weight;
+                    weight_sum += weight;
+                    samples += 1;
+
+                    let sample_luminance = luminance(&sample_color);
+                    let delta = sample_luminance - mean;
+                    mean += delta / samples as f64;
+                    squared_deviations += delta * (sample_luminance - mean);
+
+                    if self.converged(samples, mean, squared_deviations) {
+                        break;
+                    }
                 }
 
-                write_color(&(pixel_color * self.pixel_samples_scale));
+                // negative filter lobes can cancel out entirely at low sample
+                // counts
+                if weight_sum > 0. {
+                    framebuffer.push(pixel_color / weight_sum);
+                } else {
+                    framebuffer.push(Color::zero());
+                }
+                spp_map.push(samples);
+                // of the mean, which is what the denoiser cares about
+                variance.push(if samples > 1 {
+                    squared_deviations / ((samples - 1) * samples) as f64
+                } else {
+                    0.
+                });
+                aov_buffers.finish_pixel(i, j, weight_sum);
             }
         }
+
+        if let Some(denoiser) = &self.denoiser {
+            framebuffer = denoiser.denoise(
+                self.image_width,
+                self.image_height,
+                &framebuffer,
+                &variance,
+                aov_buffers.get(Aov::Albedo).unwrap(),
+                aov_buffers.get(Aov::Normal).unwrap(),
+            );
+        }
+
+        let mut out = BufWriter::new(io::stdout().lock());
+        if let Err(error) = self.color_pipeline.write_ppm(
+            &mut out,
+            self.image_width,
+            self.image_height,
+            &framebuffer,
+        ) {
+            eprintln!("Could not write the image: {error}");
+        }
+
+        if let Some(path) = &self.spp_map_path {
+            if let Err(error) = self.write_spp_map(path, &spp_map) {
+                eprintln!("Could not write the spp map to {path}: {error}");
+            }
+        }
+
+        if missing_light_split {
+            eprintln!(
+                "This integrator doesn't separate direct from indirect light; \
+                 the direct, indirect and emission AOVs are left black"
+            );
+        }
+        if let Err(error) = aov_buffers.write(&self.aov_prefix, &self.aovs) {
+            eprintln!("Could not write the AOVs: {error}");
+        }
+    }
+
+    // Compares the standard error of the pixel's mean luminance to the noise
+    // threshold. The error is taken relative to the square root of the mean,
+    // roughly how visible noise is once the image is gamma encoded, so dark
+    // pixels aren't sampled forever.
+    fn converged(&self, samples: u32, mean: f64, squared_deviations: f64) -> bool {
+        let threshold = match self.noise_threshold {
+            Some(threshold) => threshold,
+            None => return false,
+        };
+        if samples < u32::max(self.min_samples_per_pixel, 2) {
+            return false;
+        }
+
+        let variance = squared_deviations / (samples - 1) as f64;
+        let standard_error = (variance / samples as f64).sqrt();
+        standard_error / f64::max(mean, 1e-4).sqrt() < threshold
+    }
+
+    // plain PPM, black for the fewest samples allowed and white for the most
+    fn write_spp_map(&self, path: &str, spp_map: &[u32]) -> io::Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+        write!(
+            file,
+            "P3\n{} {}\n255\n",
+            self.image_width, self.image_height
+        )?;
+
+        let min = f64::min(
+            self.min_samples_per_pixel as f64,
+            self.samples_per_pixel as f64,
+        );
+        let range = f64::max(self.samples_per_pixel as f64 - min, 1.);
+        for &samples in spp_map {
+            let shade = ((samples as f64 - min) / range * 255.).clamp(0., 255.) as u32;
+            writeln!(file, "{shade} {shade} {shade}")?;
+        }
+        file.flush()
     }
 }
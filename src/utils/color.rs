@@ -1,25 +1,215 @@
+use std::io::{self, Write};
+
 use crate::utils::interval::Interval;
 
+use super::sampler::hash;
 use super::vec3::Vec3;
 
 pub type Color = Vec3;
 
-fn linear_to_gamma(linear_component: f64) -> f64 {
-    if linear_component > 0. {
-        return linear_component.sqrt();
+// relative luminance of a linear Rec. 709 color
+pub fn luminance(color: &Color) -> f64 {
+    0.2126 * color.x + 0.7152 * color.y + 0.0722 * color.z
+}
+
+// sRGB transfer function (IEC 61966-2-1), linear to display encoded
+fn linear_to_srgb(linear_component: f64) -> f64 {
+    if linear_component <= 0.0031308 {
+        return 12.92 * f64::max(linear_component, 0.);
+    }
+    1.055 * linear_component.powf(1. / 2.4) - 0.055
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ToneMapping {
+    // clip at 1
+    None,
+    Reinhard,
+    ExtendedReinhard,
+    Aces,
+    Agx,
+}
+
+impl ToneMapping {
+    pub const NAMES: [&'static str; 5] = ["none", "reinhard", "extended-reinhard", "aces", "agx"];
+
+    pub fn from_name(name: &str) -> Option<ToneMapping> {
+        match name {
+            "none" => Some(ToneMapping::None),
+            "reinhard" => Some(ToneMapping::Reinhard),
+            "extended-reinhard" => Some(ToneMapping::ExtendedReinhard),
+            "aces" => Some(ToneMapping::Aces),
+            "agx" => Some(ToneMapping::Agx),
+            _ => None,
+        }
+    }
+}
+
+// Turns linear scene radiance into 8-bit display values: exposure, then a
+// tone mapping operator into [0, 1], then the sRGB curve and quantization.
+pub struct ColorPipeline {
+    // in stops; +1 doubles the brightness
+    pub exposure: f64,
+    pub tone_mapping: ToneMapping,
+    // luminance that extended Reinhard maps to white
+    pub white_point: f64,
+    // adds up to a step of noise before quantizing to break up banding
+    pub dither: bool,
+}
+
+impl Default for ColorPipeline {
+    fn default() -> Self {
+        ColorPipeline {
+            exposure: 0.,
+            tone_mapping: ToneMapping::None,
+            white_point: 4.,
+            dither: false,
+        }
+    }
+}
+
+impl ColorPipeline {
+    // display-referred linear color in [0, 1]
+    pub fn tone_map(&self, linear: &Color) -> Color {
+        let exposed = *linear * 2f64.powf(self.exposure);
+        let mapped = match self.tone_mapping {
+            ToneMapping::None => exposed,
+            ToneMapping::Reinhard => Self::scale_luminance(&exposed, |l| l / (1. + l)),
+            ToneMapping::ExtendedReinhard => {
+                let white_squared = self.white_point * self.white_point;
+                Self::scale_luminance(&exposed, |l| l * (1. + l / white_squared) / (1. + l))
+            }
+            ToneMapping::Aces => Self::aces(&exposed),
+            ToneMapping::Agx => Self::agx(&exposed),
+        };
+
+        let unit = Interval::bounded_interval(0., 1.);
+        Color::new(
+            unit.clamp(mapped.x),
+            unit.clamp(mapped.y),
+            unit.clamp(mapped.z),
+        )
+    }
+
+    // applies a curve to the luminance and scales the color to match, which
+    // keeps hues from shifting the way a per-channel curve would
+    fn scale_luminance(color: &Color, curve: impl Fn(f64) -> f64) -> Color {
+        let l = luminance(color);
+        if l <= 0. {
+            return Color::zero();
+        }
+        *color * (curve(l) / l)
+    }
+
+    fn multiply(matrix: &[[f64; 3]; 3], color: &Color) -> Color {
+        let row = |r: &[f64; 3]| r[0] * color.x + r[1] * color.y + r[2] * color.z;
+        Color::new(row(&matrix[0]), row(&matrix[1]), row(&matrix[2]))
+    }
+
+    // Stephen Hill's fit of the ACES reference rendering and sRGB output
+    // transforms
+    fn aces(color: &Color) -> Color {
+        const INPUT: [[f64; 3]; 3] = [
+            [0.59719, 0.35458, 0.04823],
+            [0.07600, 0.90834, 0.01566],
+            [0.02840, 0.13383, 0.83777],
+        ];
+        const OUTPUT: [[f64; 3]; 3] = [
+            [1.60475, -0.53108, -0.07367],
+            [-0.10208, 1.10813, -0.00605],
+            [-0.00327, -0.07276, 1.07602],
+        ];
+        let fit = |v: f64| {
+            let a = v * (v + 0.0245786) - 0.000090537;
+            let b = v * (0.983729 * v + 0.4329510) + 0.238081;
+            a / b
+        };
+
+        let v = Self::multiply(&INPUT, color);
+        Self::multiply(&OUTPUT, &Color::new(fit(v.x), fit(v.y), fit(v.z)))
+    }
+
+    // AgX with the default look, using the polynomial fit of its sigmoid
+    // from Benjamin Wrensch's minimal AgX
+    fn agx(color: &Color) -> Color {
+        const INSET: [[f64; 3]; 3] = [
+            [0.842479062253094, 0.0784335999999992, 0.0792237451477643],
+            [0.0423282422610123, 0.878468636469772, 0.0791661274605434],
+            [0.0423756549057051, 0.0784336, 0.879142973793104],
+        ];
+        const OUTSET: [[f64; 3]; 3] = [
+            [1.19687900512017, -0.0980208811401368, -0.0990297440797205],
+            [-0.0528968517574562, 1.15190312990417, -0.0989611768448433],
+            [-0.0529716355144438, -0.0980434501171241, 1.15107367264116],
+        ];
+        const MIN_EV: f64 = -12.47393;
+        const MAX_EV: f64 = 4.026069;
+        let contrast = |v: f64| {
+            let x = (v.max(1e-10).log2().clamp(MIN_EV, MAX_EV) - MIN_EV) / (MAX_EV - MIN_EV);
+            let x2 = x * x;
+            let x4 = x2 * x2;
+            15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x + 0.4298 * x2 + 0.1191 * x
+                - 0.00232
+        };
+
+        let v = Self::multiply(&INSET, color);
+        let v = Self::multiply(
+            &OUTSET,
+            &Color::new(contrast(v.x), contrast(v.y), contrast(v.z)),
+        );
+        // the curve produces display encoded values; undo the 2.2 gamma so
+        // the sRGB step that follows doesn't apply it twice
+        Color::new(
+            v.x.max(0.).powf(2.2),
+            v.y.max(0.).powf(2.2),
+            v.z.max(0.).powf(2.2),
+        )
+    }
+
+    // 8-bit sRGB values for the linear color of pixel (i, j)
+    pub fn encode(&self, linear: &Color, i: u32, j: u32) -> [u32; 3] {
+        let display = self.tone_map(linear);
+        let channels = [display.x, display.y, display.z];
+
+        let mut bytes = [0; 3];
+        for (channel, byte) in bytes.iter_mut().enumerate() {
+            // triangular noise spanning two steps hides banding best for its
+            // strength; derived from the pixel so images stay deterministic
+            let noise = if self.dither {
+                let h = hash(&[i as u64, j as u64, channel as u64]);
+                let u1 = (h >> 40) as f64 / (1u64 << 24) as f64;
+                let u2 = (h & 0xffffff) as f64 / (1u64 << 24) as f64;
+                u1 + u2 - 1.
+            } else {
+                0.
+            };
+            let value = linear_to_srgb(channels[channel]) * 255. + noise;
+            *byte = value.round().clamp(0., 255.) as u32;
+        }
+        bytes
+    }
+
+    // plain PPM of a linear framebuffer stored row by row
+    pub fn write_ppm(
+        &self,
+        out: &mut impl Write,
+        width: u32,
+        height: u32,
+        pixels: &[Color],
+    ) -> io::Result<()> {
+        writeln!(out, "P3\n{width} {height}\n255")?;
+        for j in 0..height {
+            for i in 0..width {
+                let [r, g, b] = self.encode(&pixels[(j * width + i) as usize], i, j);
+                writeln!(out, "{r} {g} {b}")?;
+            }
+        }
+        out.flush()
     }
-    return 0.;
 }
 
 pub fn write_color(pixel_color: &Color) {
-    let r = linear_to_gamma(pixel_color.x);
-    let g = linear_to_gamma(pixel_color.y);
-    let b = linear_to_gamma(pixel_color.z);
-
-    let intensity = Interval::bounded_interval(0., 0.999);
-    let rbyte = (256. * intensity.clamp(r)) as u32;
-    let gbyte = (256. * intensity.clamp(g)) as u32;
-    let bbyte = (256. * intensity.clamp(b)) as u32;
+    let [rbyte, gbyte, bbyte] = ColorPipeline::default().encode(pixel_color, 0, 0);
 
     print!(
         "{rbyte} {gbyte} {bbyte}\n",
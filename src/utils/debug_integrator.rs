@@ -0,0 +1,128 @@
+use super::{
+    color::Color,
+    hittable::HitRecord,
+    integrator::{Integrator, Scene},
+    ray::Ray,
+    sampler::Sampler,
+    vec3::Vec3,
+};
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum DebugMode {
+    ShadingNormal,
+    GeometricNormal,
+    Depth,
+    Uv,
+    FrontFace,
+    ObjectId,
+    MaterialId,
+}
+
+impl DebugMode {
+    pub const NAMES: [&'static str; 7] = [
+        "normal",
+        "geometric-normal",
+        "depth",
+        "uv",
+        "front-face",
+        "object-id",
+        "material-id",
+    ];
+
+    pub fn from_name(name: &str) -> Option<DebugMode> {
+        match name {
+            "normal" => Some(DebugMode::ShadingNormal),
+            "geometric-normal" => Some(DebugMode::GeometricNormal),
+            "depth" => Some(DebugMode::Depth),
+            "uv" => Some(DebugMode::Uv),
+            "front-face" => Some(DebugMode::FrontFace),
+            "object-id" => Some(DebugMode::ObjectId),
+            "material-id" => Some(DebugMode::MaterialId),
+            _ => None,
+        }
+    }
+}
+
+// Shows one property of the first hit along each camera ray instead of
+// lighting, to tell geometry problems apart from material problems. Misses
+// are black.
+pub struct DebugIntegrator {
+    pub mode: DebugMode,
+    // hit distance mapped to black in Depth mode (white is distance 0)
+    pub max_distance: f64,
+}
+
+impl DebugIntegrator {
+    pub fn new(mode: DebugMode) -> DebugIntegrator {
+        DebugIntegrator {
+            mode,
+            max_distance: 10.,
+        }
+    }
+
+    fn normal_color(normal: &Vec3) -> Color {
+        (Vec3::unit_vector(normal) + Color::new(1., 1., 1.)) * 0.5
+    }
+
+    // scatters consecutive ids across the hue circle
+    pub fn false_color(id: u64) -> Color {
+        let mut h = id.wrapping_add(0x9e3779b97f4a7c15);
+        h = (h ^ (h >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        h = (h ^ (h >> 27)).wrapping_mul(0x94d049bb133111eb);
+        h ^= h >> 31;
+
+        let hue = (h >> 11) as f64 / (1u64 << 53) as f64 * 6.;
+        let x = 1. - (hue % 2. - 1.).abs();
+        match hue as u32 {
+            0 => Color::new(1., x, 0.),
+            1 => Color::new(x, 1., 0.),
+            2 => Color::new(0., 1., x),
+            3 => Color::new(0., x, 1.),
+            4 => Color::new(x, 0., 1.),
+            _ => Color::new(1., 0., x),
+        }
+    }
+
+    fn visualize(&self, ray: &Ray, hit_record: &HitRecord) -> Color {
+        match self.mode {
+            DebugMode::ShadingNormal => Self::normal_color(&hit_record.normal),
+            DebugMode::GeometricNormal => Self::normal_color(&hit_record.geometric_normal),
+            DebugMode::Depth => {
+                let distance = hit_record.t * ray.direction.norm();
+                let shade = f64::max(0., 1. - distance / self.max_distance);
+                Color::new(shade, shade, shade)
+            }
+            DebugMode::Uv => Color::new(hit_record.u, hit_record.v, 0.),
+            DebugMode::FrontFace => {
+                if hit_record.front_face {
+                    Color::new(0., 1., 0.)
+                } else {
+                    Color::new(1., 0., 0.)
+                }
+            }
+            DebugMode::ObjectId => Self::false_color(hit_record.object_id as u64),
+            DebugMode::MaterialId => match hit_record.material {
+                // materials have no ids of their own, so tell them apart by address
+                Some(material) => {
+                    Self::false_color(material as *const _ as *const () as usize as u64)
+                }
+                None => Color::zero(),
+            },
+        }
+    }
+}
+
+impl Integrator for DebugIntegrator {
+    fn li(&self, ray: &Ray, scene: &Scene, sampler: &mut dyn Sampler) -> Color {
+        let ray = &Ray {
+            seed: sampler.get_seed(),
+            ..*ray
+        };
+        let mut hit_record = HitRecord::default();
+        if !scene.hit(ray, &mut hit_record) {
+            return Color::zero();
+        }
+
+        self.visualize(ray, &hit_record)
+    }
+}
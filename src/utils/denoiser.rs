@@ -0,0 +1,118 @@
+use super::{
+    color::{luminance, Color},
+    vec3::Vec3,
+};
+
+// Edge-avoiding à-trous wavelet filter (Dammertz et al.), steered the way
+// SVGF does it: each pass blurs with a 5x5 B-spline kernel whose taps spread
+// out twice as far as the last pass's, and every tap is down-weighted when
+// its normal or albedo differs from the center pixel's, or when its
+// luminance differs by more than the center's noise level explains. The
+// noise level is the variance of the pixel's estimate, filtered along with
+// the color so later passes trust the already smoothed values more.
+#[derive(Clone)]
+pub struct Denoiser {
+    pub iterations: u32,
+    // how many standard deviations of luminance difference still blend
+    pub luminance_sigma: f64,
+    // exponent on the cosine between normals; higher keeps creases sharper
+    pub normal_power: f64,
+    pub albedo_sigma: f64,
+}
+
+impl Default for Denoiser {
+    fn default() -> Self {
+        Denoiser {
+            iterations: 5,
+            luminance_sigma: 4.,
+            normal_power: 128.,
+            albedo_sigma: 0.1,
+        }
+    }
+}
+
+impl Denoiser {
+    const KERNEL: [f64; 5] = [1. / 16., 1. / 4., 3. / 8., 1. / 4., 1. / 16.];
+
+    // `variance` is the variance of each pixel's mean luminance; all buffers
+    // are row-major and `width` * `height` long
+    pub fn denoise(
+        &self,
+        width: u32,
+        height: u32,
+        color: &[Color],
+        variance: &[f64],
+        albedo: &[Color],
+        normal: &[Color],
+    ) -> Vec<Color> {
+        let mut color = color.to_vec();
+        let mut variance = variance.to_vec();
+
+        for iteration in 0..self.iterations {
+            let step = 1i64 << iteration;
+            let mut next_color = vec![Color::zero(); color.len()];
+            let mut next_variance = vec![0.; variance.len()];
+
+            for j in 0..height as i64 {
+                for i in 0..width as i64 {
+                    let p = (j * width as i64 + i) as usize;
+                    let luminance_p = luminance(&color[p]);
+                    let noise = self.luminance_sigma * variance[p].max(0.).sqrt() + 1e-6;
+
+                    let mut color_sum = Color::zero();
+                    let mut variance_sum = 0.;
+                    let mut weight_sum = 0.;
+                    for (ky, kernel_y) in Self::KERNEL.iter().enumerate() {
+                        for (kx, kernel_x) in Self::KERNEL.iter().enumerate() {
+                            let x = i + (kx as i64 - 2) * step;
+                            let y = j + (ky as i64 - 2) * step;
+                            if x < 0 || y < 0 || x >= width as i64 || y >= height as i64 {
+                                continue;
+                            }
+                            let q = (y * width as i64 + x) as usize;
+
+                            let luminance_weight =
+                                (-(luminance_p - luminance(&color[q])).abs() / noise).exp();
+                            let weight = kernel_x
+                                * kernel_y
+                                * luminance_weight
+                                * self.normal_weight(&normal[p], &normal[q])
+                                * self.albedo_weight(&albedo[p], &albedo[q]);
+
+                            color_sum += color[q] * weight;
+                            variance_sum += variance[q] * weight * weight;
+                            weight_sum += weight;
+                        }
+                    }
+
+                    // the center tap always has a positive weight
+                    next_color[p] = color_sum / weight_sum;
+                    next_variance[p] = variance_sum / (weight_sum * weight_sum);
+                }
+            }
+
+            color = next_color;
+            variance = next_variance;
+        }
+
+        color
+    }
+
+    fn normal_weight(&self, n_p: &Vec3, n_q: &Vec3) -> f64 {
+        // pixels where nothing was hit have zero normals and only blend with
+        // each other
+        match (n_p.near_zero(), n_q.near_zero()) {
+            (true, true) => 1.,
+            (false, false) => {
+                // normals averaged over a pixel fall short of unit length
+                let cosine = Vec3::dot(&Vec3::unit_vector(n_p), &Vec3::unit_vector(n_q));
+                f64::max(0., cosine).powf(self.normal_power)
+            }
+            _ => 0.,
+        }
+    }
+
+    fn albedo_weight(&self, a_p: &Color, a_q: &Color) -> f64 {
+        (-(*a_p - *a_q).norm_squared() / (self.albedo_sigma * self.albedo_sigma)).exp()
+    }
+}
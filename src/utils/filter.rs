@@ -0,0 +1,175 @@
+use std::f64::consts::PI;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum FilterKind {
+    Box,
+    Tent,
+    Gaussian,
+    Mitchell,
+    Lanczos,
+}
+
+impl FilterKind {
+    pub const NAMES: [&'static str; 5] = ["box", "tent", "gaussian", "mitchell", "lanczos"];
+
+    pub fn from_name(name: &str) -> Option<FilterKind> {
+        match name {
+            "box" => Some(FilterKind::Box),
+            "tent" => Some(FilterKind::Tent),
+            "gaussian" => Some(FilterKind::Gaussian),
+            "mitchell" => Some(FilterKind::Mitchell),
+            "lanczos" => Some(FilterKind::Lanczos),
+            _ => None,
+        }
+    }
+
+    // radius (in pixels) each filter is usually run with
+    pub fn default_radius(&self) -> f64 {
+        match self {
+            FilterKind::Box => 0.5,
+            FilterKind::Tent => 1.,
+            FilterKind::Gaussian => 1.5,
+            FilterKind::Mitchell => 2.,
+            FilterKind::Lanczos => 3.,
+        }
+    }
+}
+
+// Pixel reconstruction filter, applied by filter importance sampling: sample
+// positions are drawn around the pixel center in proportion to |filter|, and
+// each sample carries the weight filter / pdf, which is only ever its sign
+// times a constant. Pixels are the weighted mean of their own samples, so
+// nothing is splatted into neighbours and pixels stay independent. Filters
+// are separable, with each axis tabulated once up front.
+pub struct Filter {
+    pub kind: FilterKind,
+    pub radius: f64,
+    table: FilterTable,
+}
+
+impl Filter {
+    pub fn new(kind: FilterKind, radius: f64) -> Filter {
+        let radius = f64::max(radius, 1e-3);
+        Filter {
+            kind,
+            radius,
+            table: FilterTable::new(|x| Self::evaluate(kind, radius, x), radius),
+        }
+    }
+
+    // 1D filter value at offset `x` from the pixel center
+    fn evaluate(kind: FilterKind, radius: f64, x: f64) -> f64 {
+        let x = x.abs();
+        if x > radius {
+            return 0.;
+        }
+
+        match kind {
+            FilterKind::Box => 1.,
+            FilterKind::Tent => radius - x,
+            FilterKind::Gaussian => {
+                // cut off at three standard deviations and shifted to reach
+                // zero there instead of jumping
+                let sigma = radius / 3.;
+                let gaussian = |x: f64| (-x * x / (2. * sigma * sigma)).exp();
+                gaussian(x) - gaussian(radius)
+            }
+            FilterKind::Mitchell => {
+                // B = C = 1/3, the pair Mitchell and Netravali recommend
+                let (b, c) = (1. / 3., 1. / 3.);
+                let x = 2. * x / radius;
+                if x < 1. {
+                    ((12. - 9. * b - 6. * c) * x * x * x
+                        + (-18. + 12. * b + 6. * c) * x * x
+                        + (6. - 2. * b))
+                        / 6.
+                } else {
+                    ((-b - 6. * c) * x * x * x
+                        + (6. * b + 30. * c) * x * x
+                        + (-12. * b - 48. * c) * x
+                        + (8. * b + 24. * c))
+                        / 6.
+                }
+            }
+            FilterKind::Lanczos => {
+                // sinc windowed by a sinc stretched across the radius
+                let sinc = |x: f64| {
+                    if x < 1e-5 {
+                        1.
+                    } else {
+                        (PI * x).sin() / (PI * x)
+                    }
+                };
+                sinc(x) * sinc(x / radius)
+            }
+        }
+    }
+
+    // an offset from the pixel center in [-radius, radius]^2 and its weight
+    pub fn sample(&self, u: (f64, f64)) -> (f64, f64, f64) {
+        let (x, weight_x) = self.table.sample(u.0);
+        let (y, weight_y) = self.table.sample(u.1);
+        (x, y, weight_x * weight_y)
+    }
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Filter::new(FilterKind::Box, FilterKind::Box.default_radius())
+    }
+}
+
+// piecewise-constant 1D filter over [-radius, radius] with the CDF of its
+// absolute value
+struct FilterTable {
+    radius: f64,
+    values: Vec<f64>,
+    cdf: Vec<f64>,
+    integral: f64,
+}
+
+impl FilterTable {
+    const SIZE: usize = 256;
+
+    fn new(f: impl Fn(f64) -> f64, radius: f64) -> FilterTable {
+        let width = 2. * radius / Self::SIZE as f64;
+        let values: Vec<f64> = (0..Self::SIZE)
+            .map(|k| f(-radius + (k as f64 + 0.5) * width))
+            .collect();
+
+        let mut cdf = Vec::with_capacity(Self::SIZE + 1);
+        cdf.push(0.);
+        for value in &values {
+            cdf.push(cdf.last().unwrap() + value.abs() * width);
+        }
+        let integral = *cdf.last().unwrap();
+        for c in cdf.iter_mut() {
+            *c /= integral;
+        }
+
+        FilterTable {
+            radius,
+            values,
+            cdf,
+            integral,
+        }
+    }
+
+    fn sample(&self, u: f64) -> (f64, f64) {
+        // last bin whose CDF start is at or below u, skipping empty bins
+        let bin = self.cdf.partition_point(|&c| c <= u).clamp(1, Self::SIZE) - 1;
+        let bin_mass = self.cdf[bin + 1] - self.cdf[bin];
+        let fraction = if bin_mass > 0. {
+            (u - self.cdf[bin]) / bin_mass
+        } else {
+            0.5
+        };
+
+        let width = 2. * self.radius / Self::SIZE as f64;
+        let x = -self.radius + (bin as f64 + fraction) * width;
+        let value = self.values[bin];
+        let pdf = value.abs() / self.integral;
+        let weight = if pdf > 0. { value / pdf } else { 0. };
+        (x, weight)
+    }
+}
@@ -0,0 +1,512 @@
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::path::Path;
+
+use super::aabb::Aabb;
+use super::hittable::{HitRecord, Hittable};
+use super::interval::Interval;
+use super::material::Material;
+use super::point3::Point3;
+use super::ray::Ray;
+use super::vec3::Vec3;
+
+// Terrain from a grid of elevation samples spread over the x/z extent of
+// `bounds`. A height of 0 maps to bounds.min.y and 1 to bounds.max.y. Each
+// grid cell is two triangles, but rays only visit the cells whose min-max
+// mipmap boxes they pass through.
+pub struct Heightfield<'a> {
+    pub nx: usize,
+    pub nz: usize,
+    pub bounds: Aabb,
+    pub material: Option<&'a dyn Material>,
+    heights: Vec<f64>,
+    normals: Vec<Vec3>,
+    // levels[0] holds per-cell (min, max) heights, each further level halves
+    // the resolution until a single node covers the whole field
+    levels: Vec<MinMaxLevel>,
+}
+
+struct MinMaxLevel {
+    width: usize,
+    depth: usize,
+    ranges: Vec<(f64, f64)>,
+}
+
+impl<'a> Heightfield<'a> {
+    pub fn new(
+        nx: usize,
+        nz: usize,
+        heights: Vec<f64>,
+        bounds: Aabb,
+        material: &'a dyn Material,
+    ) -> Result<Heightfield<'a>> {
+        if nx < 2 || nz < 2 {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("a {}x{} heightfield needs at least 2x2 samples", nx, nz),
+            ));
+        }
+        if heights.len() != nx * nz {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "height data does not match grid size",
+            ));
+        }
+
+        let mut heightfield = Heightfield {
+            nx,
+            nz,
+            bounds,
+            material: Some(material),
+            heights,
+            normals: Vec::new(),
+            levels: Vec::new(),
+        };
+        heightfield.normals = heightfield.compute_normals();
+        heightfield.levels = heightfield.build_min_max();
+        Ok(heightfield)
+    }
+
+    // samples `f(u, v)` with u, v in [0, 1] across the grid
+    pub fn from_fn<F: Fn(f64, f64) -> f64>(
+        nx: usize,
+        nz: usize,
+        bounds: Aabb,
+        material: &'a dyn Material,
+        f: F,
+    ) -> Result<Heightfield<'a>> {
+        let mut heights = Vec::with_capacity(nx * nz);
+        for j in 0..nz {
+            for i in 0..nx {
+                heights.push(f(i as f64 / (nx - 1) as f64, j as f64 / (nz - 1) as f64));
+            }
+        }
+
+        Heightfield::new(nx, nz, heights, bounds, material)
+    }
+
+    // headerless little-endian f32 heights, x-fastest
+    pub fn load_raw<P: AsRef<Path>>(
+        path: P,
+        nx: usize,
+        nz: usize,
+        bounds: Aabb,
+        material: &'a dyn Material,
+    ) -> Result<Heightfield<'a>> {
+        let bytes = fs::read(path)?;
+        if bytes.len() != nx * nz * 4 {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "expected {} bytes for a {}x{} heightfield, found {}",
+                    nx * nz * 4,
+                    nx,
+                    nz,
+                    bytes.len()
+                ),
+            ));
+        }
+
+        let heights = bytes
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64)
+            .collect();
+        Heightfield::new(nx, nz, heights, bounds, material)
+    }
+
+    // grayscale PGM image (ASCII P2 or binary P5), white being the highest point
+    pub fn load_pgm<P: AsRef<Path>>(
+        path: P,
+        bounds: Aabb,
+        material: &'a dyn Material,
+    ) -> Result<Heightfield<'a>> {
+        let bytes = fs::read(path)?;
+        let invalid = |message: &str| Error::new(ErrorKind::InvalidData, message.to_string());
+
+        // header: magic, width, height, maxval, separated by whitespace and comments
+        let mut fields = Vec::new();
+        let mut pos = 0;
+        while fields.len() < 4 {
+            while pos < bytes.len() && (bytes[pos].is_ascii_whitespace() || bytes[pos] == b'#') {
+                if bytes[pos] == b'#' {
+                    while pos < bytes.len() && bytes[pos] != b'\n' {
+                        pos += 1;
+                    }
+                } else {
+                    pos += 1;
+                }
+            }
+            let start = pos;
+            while pos < bytes.len() && !bytes[pos].is_ascii_whitespace() {
+                pos += 1;
+            }
+            if start == pos {
+                return Err(invalid("truncated PGM header"));
+            }
+            fields.push(String::from_utf8_lossy(&bytes[start..pos]).to_string());
+        }
+
+        let parse = |field: &String| {
+            field
+                .parse::<usize>()
+                .map_err(|_| invalid("invalid PGM header"))
+        };
+        let (nx, nz, maxval) = (parse(&fields[1])?, parse(&fields[2])?, parse(&fields[3])?);
+        if maxval == 0 || maxval > 65535 {
+            return Err(invalid("invalid PGM maxval"));
+        }
+        let count = nx * nz;
+
+        let samples: Vec<usize> = match fields[0].as_str() {
+            "P2" => String::from_utf8_lossy(&bytes[pos..])
+                .split_ascii_whitespace()
+                .take(count)
+                .map(|s| {
+                    s.parse::<usize>()
+                        .map_err(|_| invalid("invalid PGM sample"))
+                })
+                .collect::<Result<_>>()?,
+            "P5" => {
+                // exactly one whitespace byte separates the header from the data
+                let data = &bytes[usize::min(pos + 1, bytes.len())..];
+                if maxval < 256 {
+                    data.iter().take(count).map(|&b| b as usize).collect()
+                } else {
+                    data.chunks_exact(2)
+                        .take(count)
+                        .map(|b| u16::from_be_bytes([b[0], b[1]]) as usize)
+                        .collect()
+                }
+            }
+            _ => return Err(invalid("not a PGM file")),
+        };
+        if samples.len() != count {
+            return Err(invalid("truncated PGM data"));
+        }
+
+        let heights = samples.iter().map(|&s| s as f64 / maxval as f64).collect();
+        Heightfield::new(nx, nz, heights, bounds, material)
+    }
+
+    fn height(&self, i: usize, j: usize) -> f64 {
+        self.heights[j * self.nx + i]
+    }
+
+    fn vertex(&self, i: usize, j: usize) -> Point3 {
+        let extent = self.bounds.max - self.bounds.min;
+        Point3::new(
+            self.bounds.min.x + extent.x * i as f64 / (self.nx - 1) as f64,
+            self.bounds.min.y + extent.y * self.height(i, j),
+            self.bounds.min.z + extent.z * j as f64 / (self.nz - 1) as f64,
+        )
+    }
+
+    // per-vertex normals from central differences of the world-space surface
+    fn compute_normals(&self) -> Vec<Vec3> {
+        let mut normals = Vec::with_capacity(self.nx * self.nz);
+        for j in 0..self.nz {
+            for i in 0..self.nx {
+                let (i0, i1) = (i.saturating_sub(1), usize::min(i + 1, self.nx - 1));
+                let (j0, j1) = (j.saturating_sub(1), usize::min(j + 1, self.nz - 1));
+                let along_x = self.vertex(i1, j) - self.vertex(i0, j);
+                let along_z = self.vertex(i, j1) - self.vertex(i, j0);
+                normals.push(Vec3::unit_vector(&Vec3::cross(&along_z, &along_x)));
+            }
+        }
+        normals
+    }
+
+    fn build_min_max(&self) -> Vec<MinMaxLevel> {
+        let mut ranges = Vec::with_capacity((self.nx - 1) * (self.nz - 1));
+        for j in 0..self.nz - 1 {
+            for i in 0..self.nx - 1 {
+                let corners = [
+                    self.height(i, j),
+                    self.height(i + 1, j),
+                    self.height(i, j + 1),
+                    self.height(i + 1, j + 1),
+                ];
+                let min = corners.iter().cloned().fold(f64::INFINITY, f64::min);
+                let max = corners.iter().cloned().fold(-f64::INFINITY, f64::max);
+                ranges.push((min, max));
+            }
+        }
+
+        let mut levels = vec![MinMaxLevel {
+            width: self.nx - 1,
+            depth: self.nz - 1,
+            ranges,
+        }];
+        while levels.last().unwrap().width > 1 || levels.last().unwrap().depth > 1 {
+            let finer = levels.last().unwrap();
+            let width = finer.width.div_ceil(2);
+            let depth = finer.depth.div_ceil(2);
+            let mut ranges = Vec::with_capacity(width * depth);
+            for j in 0..depth {
+                for i in 0..width {
+                    let mut range = (f64::INFINITY, -f64::INFINITY);
+                    for (ci, cj) in [
+                        (2 * i, 2 * j),
+                        (2 * i + 1, 2 * j),
+                        (2 * i, 2 * j + 1),
+                        (2 * i + 1, 2 * j + 1),
+                    ] {
+                        if ci < finer.width && cj < finer.depth {
+                            let child = finer.ranges[cj * finer.width + ci];
+                            range = (f64::min(range.0, child.0), f64::max(range.1, child.1));
+                        }
+                    }
+                    ranges.push(range);
+                }
+            }
+            levels.push(MinMaxLevel {
+                width,
+                depth,
+                ranges,
+            });
+        }
+        levels
+    }
+
+    // world-space box of mipmap node (i, j) at `level`
+    fn node_box(&self, level: usize, i: usize, j: usize) -> Aabb {
+        let cells = 1 << level;
+        let (min_h, max_h) = self.levels[level].ranges[j * self.levels[level].width + i];
+        let extent = self.bounds.max - self.bounds.min;
+        let cell_x = extent.x / (self.nx - 1) as f64;
+        let cell_z = extent.z / (self.nz - 1) as f64;
+        // padded so flat nodes still have some thickness for the slab test
+        let pad = 1e-6;
+
+        Aabb::new(
+            Point3::new(
+                self.bounds.min.x + cell_x * (i * cells) as f64,
+                self.bounds.min.y + extent.y * min_h - pad,
+                self.bounds.min.z + cell_z * (j * cells) as f64,
+            ),
+            Point3::new(
+                self.bounds.min.x + cell_x * usize::min((i + 1) * cells, self.nx - 1) as f64,
+                self.bounds.min.y + extent.y * max_h + pad,
+                self.bounds.min.z + cell_z * usize::min((j + 1) * cells, self.nz - 1) as f64,
+            ),
+        )
+    }
+
+    // descends the min-max mipmap, nearer children first, shrinking
+    // ray_t.max on every hit so farther nodes get culled by their boxes
+    fn traverse(
+        &self,
+        level: usize,
+        i: usize,
+        j: usize,
+        ray: &Ray,
+        ray_t: &mut Interval,
+        hit_record: &mut HitRecord<'a>,
+    ) -> bool {
+        if self.node_box(level, i, j).hit(ray, *ray_t).is_none() {
+            return false;
+        }
+        if level == 0 {
+            return self.hit_cell(i, j, ray, ray_t, hit_record);
+        }
+
+        let finer = &self.levels[level - 1];
+        let xs = if ray.direction.x >= 0. {
+            [2 * i, 2 * i + 1]
+        } else {
+            [2 * i + 1, 2 * i]
+        };
+        let zs = if ray.direction.z >= 0. {
+            [2 * j, 2 * j + 1]
+        } else {
+            [2 * j + 1, 2 * j]
+        };
+
+        let mut hit_anything = false;
+        for cj in zs {
+            for ci in xs {
+                if ci < finer.width
+                    && cj < finer.depth
+                    && self.traverse(level - 1, ci, cj, ray, ray_t, hit_record)
+                {
+                    hit_anything = true;
+                }
+            }
+        }
+        hit_anything
+    }
+
+    fn hit_cell(
+        &self,
+        i: usize,
+        j: usize,
+        ray: &Ray,
+        ray_t: &mut Interval,
+        hit_record: &mut HitRecord<'a>,
+    ) -> bool {
+        let corners = [(i, j), (i + 1, j), (i + 1, j + 1), (i, j + 1)];
+        let mut hit_anything = false;
+        for triangle in [[0, 1, 2], [0, 2, 3]] {
+            let [a, b, c] = triangle.map(|k| corners[k]);
+            if self.hit_triangle([a, b, c], ray, ray_t, hit_record) {
+                hit_anything = true;
+            }
+        }
+        hit_anything
+    }
+
+    // Moller-Trumbore, interpolating vertex normals and grid UVs
+    fn hit_triangle(
+        &self,
+        vertices: [(usize, usize); 3],
+        ray: &Ray,
+        ray_t: &mut Interval,
+        hit_record: &mut HitRecord<'a>,
+    ) -> bool {
+        let [p0, p1, p2] = vertices.map(|(i, j)| self.vertex(i, j));
+        let edge1 = p1 - p0;
+        let edge2 = p2 - p0;
+
+        let pvec = Vec3::cross(&ray.direction, &edge2);
+        let det = Vec3::dot(&edge1, &pvec);
+        if det.abs() < 1e-12 {
+            return false;
+        }
+        let inv_det = 1. / det;
+
+        let tvec = ray.origin - p0;
+        let b1 = Vec3::dot(&tvec, &pvec) * inv_det;
+        if !(0. ..=1.).contains(&b1) {
+            return false;
+        }
+        let qvec = Vec3::cross(&tvec, &edge1);
+        let b2 = Vec3::dot(&ray.direction, &qvec) * inv_det;
+        if b2 < 0. || b1 + b2 > 1. {
+            return false;
+        }
+        let t = Vec3::dot(&edge2, &qvec) * inv_det;
+        if !ray_t.surrounds(t) {
+            return false;
+        }
+        let b0 = 1. - b1 - b2;
+
+        let [n0, n1, n2] = vertices.map(|(i, j)| self.normals[j * self.nx + i]);
+        let [uv0, uv1, uv2] = vertices.map(|(i, j)| {
+            (
+                i as f64 / (self.nx - 1) as f64,
+                j as f64 / (self.nz - 1) as f64,
+            )
+        });
+
+        // Set hit record data
+        ray_t.max = t;
+        hit_record.t = t;
+        hit_record.p = ray.at(t);
+        let face_normal = Vec3::unit_vector(&Vec3::cross(&edge2, &edge1));
+        hit_record.set_face_normal(ray, &face_normal);
+        let shading_normal = Vec3::unit_vector(&(n0 * b0 + n1 * b1 + n2 * b2));
+        hit_record.set_shading_normal(&shading_normal);
+        hit_record.u = uv0.0 * b0 + uv1.0 * b1 + uv2.0 * b2;
+        hit_record.v = uv0.1 * b0 + uv1.1 * b1 + uv2.1 * b2;
+        // dp/du from how the uvs change along the two edges
+        let (du1, dv1) = (uv1.0 - uv0.0, uv1.1 - uv0.1);
+        let (du2, dv2) = (uv2.0 - uv0.0, uv2.1 - uv0.1);
+        hit_record.tangent = (edge1 * dv2 - edge2 * dv1) / (du1 * dv2 - dv1 * du2);
+        hit_record.material = self.material;
+
+        true
+    }
+}
+
+impl<'a> Hittable<'a> for Heightfield<'a> {
+    fn hit(&self, ray: &Ray, ray_t: Interval, hit_record: &mut HitRecord<'a>) -> bool {
+        let mut ray_t = ray_t;
+        let top = self.levels.len() - 1;
+        self.traverse(top, 0, 0, ray, &mut ray_t, hit_record)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::utils::{
+        aabb::Aabb,
+        heightfield::Heightfield,
+        hittable::{HitRecord, Hittable},
+        interval::Interval,
+        material::Lambertian,
+        point3::Point3,
+        ray::Ray,
+        sampler::{IndependentSampler, Sampler},
+    };
+
+    #[test]
+    fn test_bad_input_is_rejected() {
+        let material = Lambertian::default();
+        let bounds = Aabb::new(Point3::new(0., 0., 0.), Point3::new(1., 1., 1.));
+        assert!(Heightfield::new(1, 4, vec![0.; 4], bounds, &material).is_err());
+        assert!(Heightfield::new(3, 3, vec![0.; 8], bounds, &material).is_err());
+
+        // a single row of samples read from disk
+        let path = std::env::temp_dir().join("heightfield_test_1x3.pgm");
+        fs::write(&path, "P2\n1 3\n255\n0 128 255\n").unwrap();
+        let loaded = Heightfield::load_pgm(&path, bounds, &material);
+        fs::remove_file(&path).unwrap();
+        assert!(loaded.is_err());
+    }
+
+    #[test]
+    fn test_min_max_traversal_matches_every_cell() {
+        let material = Lambertian::default();
+        let bounds = Aabb::new(Point3::new(-2., 0., -2.), Point3::new(2., 1., 2.));
+        // odd sizes leave partial nodes along the edges of each level
+        let heightfield = Heightfield::from_fn(23, 17, bounds, &material, |u, v| {
+            0.5 + 0.3 * f64::sin(9. * u) * f64::cos(7. * v) + 0.2 * f64::sin(31. * u * v)
+        })
+        .unwrap();
+
+        let mut sampler = IndependentSampler::new(7);
+        let mut hits = 0;
+        for _ in 0..2000 {
+            let origin = Point3::new(
+                6. * sampler.get_1d() - 3.,
+                1.5 * sampler.get_1d(),
+                6. * sampler.get_1d() - 3.,
+            );
+            let target = Point3::new(
+                4. * sampler.get_1d() - 2.,
+                sampler.get_1d(),
+                4. * sampler.get_1d() - 2.,
+            );
+            let ray = Ray::new(origin, target - origin);
+            let ray_t = Interval {
+                min: 0.001,
+                max: f64::INFINITY,
+            };
+
+            let mut traversed = HitRecord::default();
+            let traversal_hit = heightfield.hit(&ray, ray_t, &mut traversed);
+
+            let mut brute_force = HitRecord::default();
+            let mut brute_force_t = ray_t;
+            let mut brute_force_hit = false;
+            for j in 0..heightfield.nz - 1 {
+                for i in 0..heightfield.nx - 1 {
+                    if heightfield.hit_cell(i, j, &ray, &mut brute_force_t, &mut brute_force) {
+                        brute_force_hit = true;
+                    }
+                }
+            }
+
+            assert_eq!(traversal_hit, brute_force_hit);
+            if traversal_hit {
+                hits += 1;
+                assert!((traversed.t - brute_force.t).abs() < 1e-9);
+                assert!((traversed.normal - brute_force.normal).norm() < 1e-9);
+            }
+        }
+        // the rays must actually exercise the traversal
+        assert!(hits > 500);
+    }
+}
@@ -1,47 +1,115 @@
 use super::interval::Interval;
 use super::material::Material;
+use super::onb::Onb;
+use super::sampler::Sampler;
 use super::{point3::Point3, ray::Ray, vec3::Vec3};
 
 #[derive(Copy, Clone)]
 pub struct HitRecord<'a> {
     pub p: Point3,
+    // shading normal, which may be interpolated or perturbed
     pub normal: Vec3,
+    // normal of the actual surface; both face against the incoming ray
+    pub geometric_normal: Vec3,
+    // dp/du, the direction u grows in, which orients anisotropic materials;
+    // zero on surfaces without a parameterization
+    pub tangent: Vec3,
     pub material: Option<&'a dyn Material>,
     pub t: f64,
+    pub u: f64,
+    pub v: f64,
     pub front_face: bool,
+    // a collision inside a participating medium rather than a surface hit;
+    // set_face_normal clears it, so only media need to set it
+    pub in_medium: bool,
+    // index of the hit object within the outermost HittableList
+    pub object_id: usize,
 }
 
 pub trait Hittable<'a> {
     fn hit(&self, ray: &Ray, ray_t: Interval, hit_record: &mut HitRecord<'a>) -> bool;
+
+    // hit for shadow rays: media that can say how much light gets through
+    // them are passed over here and accounted for by transmittance instead
+    fn hit_opaque(&self, ray: &Ray, ray_t: Interval, hit_record: &mut HitRecord<'a>) -> bool {
+        self.hit(ray, ray_t, hit_record)
+    }
+
+    // fraction of light that makes it through whatever hit_opaque passes
+    // over along `ray` within `ray_t`
+    fn transmittance(&self, _ray: &Ray, _ray_t: Interval) -> f64 {
+        1.
+    }
+
+    // solid angle density of random() picking `direction` from `origin`;
+    // objects that can't be sampled as lights keep the zero default
+    fn pdf_value(&self, _origin: &Point3, _direction: &Vec3) -> f64 {
+        0.
+    }
+
+    // direction from `origin` towards a random point on the object
+    fn random(&self, _origin: &Point3, _sampler: &mut dyn Sampler) -> Vec3 {
+        Vec3::new(1., 0., 0.)
+    }
 }
 
 impl HitRecord<'_> {
     pub fn set_face_normal(&mut self, ray: &Ray, outward_normal: &Vec3) {
+        self.in_medium = false;
         self.front_face = Vec3::dot(&ray.direction, &outward_normal) < 0.;
         self.normal = if self.front_face {
             *outward_normal
         } else {
             -*outward_normal
         };
+        self.geometric_normal = self.normal;
+    }
+
+    // overrides the shading normal after set_face_normal, flipped to the same side
+    pub fn set_shading_normal(&mut self, outward_normal: &Vec3) {
+        self.normal = if self.front_face {
+            *outward_normal
+        } else {
+            -*outward_normal
+        };
+    }
+
+    // local frame around the shading normal, lined up with the tangent
+    pub fn shading_frame(&self) -> Onb {
+        Onb::from_tangent(&self.normal, &self.tangent)
     }
 
     pub fn default<'a>() -> HitRecord<'a> {
         HitRecord {
             p: Point3::default(),
             normal: Vec3::default(),
+            geometric_normal: Vec3::default(),
+            tangent: Vec3::default(),
             material: None,
             t: f64::default(),
+            u: f64::default(),
+            v: f64::default(),
             front_face: bool::default(),
+            in_medium: false,
+            object_id: usize::default(),
         }
     }
 }
 
-#[derive(Default)]
-pub struct HittableList<'a, T: Hittable<'a>> {
+// T may be `dyn Hittable<'a>` to mix different kinds of objects in one list
+pub struct HittableList<'a, T: Hittable<'a> + ?Sized> {
     pub objects: Vec<&'a T>,
 }
 
-impl<'a, T: Hittable<'a>> HittableList<'a, T> {
+impl<'a, T: Hittable<'a> + ?Sized> Default for HittableList<'a, T> {
+    fn default() -> Self {
+        HittableList {
+            objects: Vec::new(),
+        }
+    }
+}
+
+impl<'a, T: Hittable<'a> + ?Sized> HittableList<'a, T> {
     pub fn clear(&mut self) {
         self.objects.clear();
     }
@@ -49,26 +117,74 @@ impl<'a, T: Hittable<'a>> HittableList<'a, T> {
     pub fn add(&mut self, object: &'a T) {
         self.objects.push(object);
     }
-}
 
-impl<'a, T: Hittable<'a>> Hittable<'a> for HittableList<'a, T> {
-    fn hit(&self, ray: &Ray, ray_t: Interval, hit_record: &mut HitRecord<'a>) -> bool {
+    // the closest hit any object reports through `hit_object`
+    fn closest_hit(
+        &self,
+        ray: &Ray,
+        ray_t: Interval,
+        hit_record: &mut HitRecord<'a>,
+        hit_object: impl Fn(&T, &Ray, Interval, &mut HitRecord<'a>) -> bool,
+    ) -> bool {
         let mut some_record = HitRecord::default();
         let mut hit_anything = false;
         let mut closest_so_far = ray_t.max;
 
-        for object in &self.objects {
+        for (index, object) in self.objects.iter().enumerate() {
             let interval = Interval {
                 min: ray_t.min,
                 max: closest_so_far,
             };
-            if object.hit(ray, interval, &mut some_record) {
+            if hit_object(object, ray, interval, &mut some_record) {
                 hit_anything = true;
                 closest_so_far = some_record.t;
                 *hit_record = some_record.clone();
+                hit_record.object_id = index;
             }
         }
 
         return hit_anything;
     }
 }
+
+impl<'a, T: Hittable<'a> + ?Sized> Hittable<'a> for HittableList<'a, T> {
+    fn hit(&self, ray: &Ray, ray_t: Interval, hit_record: &mut HitRecord<'a>) -> bool {
+        self.closest_hit(ray, ray_t, hit_record, |object, ray, interval, record| {
+            object.hit(ray, interval, record)
+        })
+    }
+
+    fn hit_opaque(&self, ray: &Ray, ray_t: Interval, hit_record: &mut HitRecord<'a>) -> bool {
+        self.closest_hit(ray, ray_t, hit_record, |object, ray, interval, record| {
+            object.hit_opaque(ray, interval, record)
+        })
+    }
+
+    fn transmittance(&self, ray: &Ray, ray_t: Interval) -> f64 {
+        self.objects
+            .iter()
+            .map(|object| object.transmittance(ray, ray_t))
+            .product()
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        if self.objects.is_empty() {
+            return 0.;
+        }
+
+        let weight = 1. / self.objects.len() as f64;
+        self.objects
+            .iter()
+            .map(|object| weight * object.pdf_value(origin, direction))
+            .sum()
+    }
+
+    fn random(&self, origin: &Point3, sampler: &mut dyn Sampler) -> Vec3 {
+        if self.objects.is_empty() {
+            return Vec3::new(1., 0., 0.);
+        }
+
+        let index = Vec3::random_coordinate(sampler, 0., self.objects.len() as f64) as usize;
+        self.objects[usize::min(index, self.objects.len() - 1)].random(origin, sampler)
+    }
+}
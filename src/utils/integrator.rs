@@ -0,0 +1,488 @@
+use super::{
+    color::Color,
+    hittable::{HitRecord, Hittable},
+    interval::Interval,
+    material::ScatterRecord,
+    pdf::{power_heuristic, CosinePdf, Pdf},
+    ray::Ray,
+    sampler::Sampler,
+    spectrum::SampledWavelengths,
+    vec3::Vec3,
+};
+
+// Everything an integrator needs to know about what it is rendering.
+pub struct Scene<'a, 'b> {
+    pub world: &'a dyn Hittable<'b>,
+    // emitters to sample directly; may be empty
+    pub lights: &'a dyn Hittable<'b>,
+    // solid color behind the scene; None is the sky gradient
+    pub background: Option<Color>,
+    // most bounces a path may take
+    pub max_depth: u32,
+}
+
+impl<'b> Scene<'_, 'b> {
+    pub fn hit(&self, ray: &Ray, hit_record: &mut HitRecord<'b>) -> bool {
+        let interval = Interval {
+            min: 0.001,
+            max: f64::INFINITY,
+        };
+        self.world.hit(ray, interval, hit_record)
+    }
+
+    pub fn background_color(&self, ray: &Ray) -> Color {
+        if let Some(background) = self.background {
+            return background;
+        }
+
+        let unit_direction = Vec3::unit_vector(&ray.direction);
+        let a = unit_direction.y * 0.5 + 1.;
+        Color {
+            x: 1.,
+            y: 1.,
+            z: 1.,
+        } * (1. - a)
+            + Color {
+                x: 0.5,
+                y: 0.7,
+                z: 1.0,
+            } * a
+    }
+
+    // next-event estimation: pick a point on a light, trace a shadow ray to it
+    // and weight what arrives against the odds of BSDF sampling finding it.
+    // With `wavelengths` the result is spectral, see upsample.
+    pub fn sample_lights(
+        &self,
+        ray_in: &Ray,
+        hit_record: &HitRecord,
+        sampler: &mut dyn Sampler,
+        wavelengths: Option<&SampledWavelengths>,
+    ) -> Color {
+        let direction = self.lights.random(&hit_record.p, sampler);
+        let light_pdf = self.lights.pdf_value(&hit_record.p, &direction);
+        if light_pdf <= 0. {
+            return Color::zero();
+        }
+
+        let material = hit_record.material.unwrap();
+        let shadow_ray = Ray {
+            seed: sampler.get_seed(),
+            ..Ray::new(hit_record.p, direction)
+        };
+        let bsdf_cos = material.eval(ray_in, hit_record, &shadow_ray);
+        if bsdf_cos.near_zero() {
+            return Color::zero();
+        }
+
+        // whatever the shadow ray hits first is what gets seen, so occluders
+        // simply contribute their own (usually zero) emission; media in
+        // between dim it rather than block it
+        let mut light_record = HitRecord::default();
+        let interval = Interval {
+            min: 0.001,
+            max: f64::INFINITY,
+        };
+        if !self
+            .world
+            .hit_opaque(&shadow_ray, interval, &mut light_record)
+        {
+            return Color::zero();
+        }
+        let transmittance = self.world.transmittance(
+            &shadow_ray,
+            Interval {
+                min: interval.min,
+                max: light_record.t,
+            },
+        );
+        let emitted = light_record
+            .material
+            .unwrap()
+            .emitted(&shadow_ray, &light_record)
+            * transmittance;
+
+        let bsdf_pdf = material.scattering_pdf(ray_in, hit_record, &shadow_ray);
+        let bsdf_wavelengths = if material.spectral(ray_in, hit_record) {
+            None
+        } else {
+            wavelengths
+        };
+        upsample(wavelengths, emitted)
+            * upsample(bsdf_wavelengths, bsdf_cos)
+            * power_heuristic(light_pdf, bsdf_pdf)
+            / light_pdf
+    }
+}
+
+// MIS weight for light emitted where a BSDF-sampled ray ended up, given the
+// `weight` the sampling strategies agreed on. Shadow rays pass through media,
+// so light sampling never finds what a medium emits and BSDF sampling has to
+// count all of it.
+fn emitted_weight(hit_record: &HitRecord, weight: f64) -> f64 {
+    if hit_record.in_medium {
+        1.
+    } else {
+        weight
+    }
+}
+
+// An RGB value from a material, light or the background as a path carries
+// it: unchanged, or as a spectrum at the path's wavelengths when rendering
+// spectrally. Upsampling each factor before multiplying, rather than the
+// product, is what lets colors mix the way spectra do.
+fn upsample(wavelengths: Option<&SampledWavelengths>, rgb: Color) -> Color {
+    match wavelengths {
+        Some(wavelengths) => wavelengths.upsample(&rgb),
+        None => rgb,
+    }
+}
+
+// Radiance along a camera ray split by how the light got there; the three
+// parts add up to what li returns.
+#[derive(Default)]
+pub struct LightSplit {
+    // emitters and background seen directly or only through specular bounces
+    pub emission: Color,
+    // light reaching the first non-specular surface straight from an emitter
+    pub direct: Color,
+    // everything that bounced off more than one non-specular surface
+    pub indirect: Color,
+}
+
+impl LightSplit {
+    pub fn total(&self) -> Color {
+        self.emission + self.direct + self.indirect
+    }
+}
+
+// Computes the radiance arriving along a camera ray. Camera::render averages
+// one call per sample, so swapping the integrator changes what gets rendered.
+pub trait Integrator {
+    fn li(&self, ray: &Ray, scene: &Scene, sampler: &mut dyn Sampler) -> Color;
+
+    // li split up for the light AOVs; integrators that don't keep track
+    // return None without drawing any samples
+    fn li_split(
+        &self,
+        _ray: &Ray,
+        _scene: &Scene,
+        _sampler: &mut dyn Sampler,
+    ) -> Option<LightSplit> {
+        None
+    }
+}
+
+// Recursive unidirectional path tracer with next-event estimation at every
+// non-specular bounce, combined with BSDF sampling by MIS.
+pub struct PathTracer;
+
+impl PathTracer {
+    // `emission_weight` is the MIS weight for emitted light this ray runs into,
+    // below 1 when the previous bounce could also have reached that light by
+    // sampling it directly
+    fn ray_color(
+        &self,
+        ray: &Ray,
+        depth: u32,
+        scene: &Scene,
+        sampler: &mut dyn Sampler,
+        emission_weight: f64,
+    ) -> Color {
+        if depth == 0 {
+            return Color::zero();
+        }
+
+        let ray = &Ray {
+            seed: sampler.get_seed(),
+            ..*ray
+        };
+        let mut hit_record = HitRecord::default();
+        if !scene.hit(ray, &mut hit_record) {
+            return scene.background_color(ray);
+        }
+
+        let mut scatter_record = ScatterRecord::default();
+        let material = hit_record.material.unwrap();
+        let emitted =
+            material.emitted(ray, &hit_record) * emitted_weight(&hit_record, emission_weight);
+        if !material.scatter(ray, &hit_record, &mut scatter_record, sampler) {
+            return emitted;
+        }
+
+        let pdf = match &scatter_record.pdf {
+            Some(pdf) => pdf,
+            None => {
+                return emitted
+                    + self.ray_color(&scatter_record.skip_pdf_ray, depth - 1, scene, sampler, 1.)
+                        * scatter_record.attenuation;
+            }
+        };
+
+        let direct = scene.sample_lights(ray, &hit_record, sampler, None);
+
+        let scattered = Ray::new(hit_record.p, pdf.generate(sampler));
+        let pdf_value = pdf.value(&scattered.direction);
+        if pdf_value <= 0. {
+            return emitted + direct;
+        }
+
+        let bsdf_cos = material.eval(ray, &hit_record, &scattered);
+        let light_pdf = scene.lights.pdf_value(&hit_record.p, &scattered.direction);
+        let weight = power_heuristic(pdf_value, light_pdf);
+        let indirect = self.ray_color(&scattered, depth - 1, scene, sampler, weight);
+
+        emitted + direct + indirect * bsdf_cos / pdf_value
+    }
+}
+
+impl Integrator for PathTracer {
+    fn li(&self, ray: &Ray, scene: &Scene, sampler: &mut dyn Sampler) -> Color {
+        self.ray_color(ray, scene.max_depth, scene, sampler, 1.)
+    }
+}
+
+// The same estimator as PathTracer written as a loop: the path's throughput
+// is carried forward instead of multiplied in on the way back up. Past
+// `rr_min_depth` bounces, paths are ended at random with a probability that
+// grows as their throughput drops, and survivors are boosted to compensate,
+// so long paths stay unbiased without paying for every dim bounce.
+// The scene's `max_depth` is only a safety net.
+//
+// With `spectral` set, each path carries three sampled wavelengths instead
+// of RGB and is converted back to RGB at the end, which is what lets
+// dispersive glass split white light into colors.
+pub struct IterativePathTracer {
+    pub rr_min_depth: u32,
+    pub spectral: bool,
+}
+
+impl Default for IterativePathTracer {
+    fn default() -> IterativePathTracer {
+        IterativePathTracer {
+            rr_min_depth: 3,
+            spectral: false,
+        }
+    }
+}
+
+impl IterativePathTracer {
+    fn trace(&self, ray: &Ray, scene: &Scene, sampler: &mut dyn Sampler) -> LightSplit {
+        let mut split = LightSplit::default();
+        let mut throughput = Color::new(1., 1., 1.);
+        let mut ray = *ray;
+        let mut wavelengths = self
+            .spectral
+            .then(|| SampledWavelengths::sample_visible(sampler.get_1d()));
+        // MIS weight for emission found by the current ray, see PathTracer
+        let mut emission_weight = 1.;
+        // non-specular vertices so far, which decides where light is filed
+        let mut diffuse_bounces = 0;
+        let file =
+            |split: &mut LightSplit, diffuse_bounces: u32, light: Color| match diffuse_bounces {
+                0 => split.emission += light,
+                1 => split.direct += light,
+                _ => split.indirect += light,
+            };
+
+        for depth in 0..scene.max_depth {
+            ray.wavelengths = wavelengths;
+            ray.seed = sampler.get_seed();
+            let mut hit_record = HitRecord::default();
+            if !scene.hit(&ray, &mut hit_record) {
+                let background =
+                    throughput * upsample(wavelengths.as_ref(), scene.background_color(&ray));
+                file(&mut split, diffuse_bounces, background);
+                break;
+            }
+
+            let mut scatter_record = ScatterRecord::default();
+            let material = hit_record.material.unwrap();
+            let emitted = throughput
+                * upsample(wavelengths.as_ref(), material.emitted(&ray, &hit_record))
+                * emitted_weight(&hit_record, emission_weight);
+            file(&mut split, diffuse_bounces, emitted);
+            if !material.scatter(&ray, &hit_record, &mut scatter_record, sampler) {
+                break;
+            }
+            if scatter_record.dispersive {
+                if let Some(wavelengths) = wavelengths.as_mut() {
+                    wavelengths.terminate_secondary();
+                }
+            }
+            // materials that answer per wavelength need no upsampling
+            let bsdf_wavelengths = if material.spectral(&ray, &hit_record) {
+                None
+            } else {
+                wavelengths.as_ref()
+            };
+
+            match &scatter_record.pdf {
+                None => {
+                    throughput *= upsample(bsdf_wavelengths, scatter_record.attenuation);
+                    ray = scatter_record.skip_pdf_ray;
+                    emission_weight = 1.;
+                }
+                Some(pdf) => {
+                    diffuse_bounces += 1;
+                    let direct = throughput
+                        * scene.sample_lights(&ray, &hit_record, sampler, wavelengths.as_ref());
+                    file(&mut split, diffuse_bounces, direct);
+
+                    let scattered = Ray::new(hit_record.p, pdf.generate(sampler));
+                    let pdf_value = pdf.value(&scattered.direction);
+                    if pdf_value <= 0. {
+                        break;
+                    }
+
+                    throughput *= upsample(
+                        bsdf_wavelengths,
+                        material.eval(&ray, &hit_record, &scattered),
+                    ) / pdf_value;
+                    let light_pdf = scene.lights.pdf_value(&hit_record.p, &scattered.direction);
+                    emission_weight = power_heuristic(pdf_value, light_pdf);
+                    ray = scattered;
+                }
+            }
+
+            // Russian roulette; always terminating with some probability keeps
+            // lossless specular chains from running to max_depth
+            if depth + 1 >= self.rr_min_depth {
+                let survival = f64::min(0.95, throughput.max_component());
+                if sampler.get_1d() >= survival {
+                    break;
+                }
+                throughput = throughput / survival;
+            }
+        }
+
+        if let Some(wavelengths) = wavelengths {
+            split.emission = wavelengths.to_rgb(&split.emission);
+            split.direct = wavelengths.to_rgb(&split.direct);
+            split.indirect = wavelengths.to_rgb(&split.indirect);
+        }
+        split
+    }
+}
+
+impl Integrator for IterativePathTracer {
+    fn li(&self, ray: &Ray, scene: &Scene, sampler: &mut dyn Sampler) -> Color {
+        self.trace(ray, scene, sampler).total()
+    }
+
+    fn li_split(&self, ray: &Ray, scene: &Scene, sampler: &mut dyn Sampler) -> Option<LightSplit> {
+        Some(self.trace(ray, scene, sampler))
+    }
+}
+
+// Single-bounce lighting: emission plus light arriving straight from the
+// lights (or the background) at the first non-specular surface. Specular
+// chains are still followed so glass and mirrors show what they reflect.
+pub struct DirectLighting;
+
+impl DirectLighting {
+    fn ray_color(&self, ray: &Ray, depth: u32, scene: &Scene, sampler: &mut dyn Sampler) -> Color {
+        if depth == 0 {
+            return Color::zero();
+        }
+
+        let ray = &Ray {
+            seed: sampler.get_seed(),
+            ..*ray
+        };
+        let mut hit_record = HitRecord::default();
+        if !scene.hit(ray, &mut hit_record) {
+            return scene.background_color(ray);
+        }
+
+        let mut scatter_record = ScatterRecord::default();
+        let material = hit_record.material.unwrap();
+        let emitted = material.emitted(ray, &hit_record);
+        if !material.scatter(ray, &hit_record, &mut scatter_record, sampler) {
+            return emitted;
+        }
+
+        let pdf = match &scatter_record.pdf {
+            Some(pdf) => pdf,
+            None => {
+                return emitted
+                    + self.ray_color(&scatter_record.skip_pdf_ray, depth - 1, scene, sampler)
+                        * scatter_record.attenuation;
+            }
+        };
+
+        let direct = scene.sample_lights(ray, &hit_record, sampler, None);
+
+        // the BSDF-sampled half of MIS only counts light found by this one ray
+        let scattered = Ray {
+            seed: sampler.get_seed(),
+            ..Ray::new(hit_record.p, pdf.generate(sampler))
+        };
+        let pdf_value = pdf.value(&scattered.direction);
+        if pdf_value <= 0. {
+            return emitted + direct;
+        }
+
+        let mut next_record = HitRecord::default();
+        let incoming = if scene.hit(&scattered, &mut next_record) {
+            let light_pdf = scene.lights.pdf_value(&hit_record.p, &scattered.direction);
+            next_record
+                .material
+                .unwrap()
+                .emitted(&scattered, &next_record)
+                * emitted_weight(&next_record, power_heuristic(pdf_value, light_pdf))
+        } else {
+            scene.background_color(&scattered)
+        };
+        let bsdf_cos = material.eval(ray, &hit_record, &scattered);
+
+        emitted + direct + incoming * bsdf_cos / pdf_value
+    }
+}
+
+impl Integrator for DirectLighting {
+    fn li(&self, ray: &Ray, scene: &Scene, sampler: &mut dyn Sampler) -> Color {
+        self.ray_color(ray, scene.max_depth, scene, sampler)
+    }
+}
+
+// Clay-style ambient occlusion: the fraction of cosine-weighted rays from the
+// first hit that escape within `max_distance`. Materials are ignored.
+pub struct AmbientOcclusion {
+    pub samples: u32,
+    pub max_distance: f64,
+}
+
+impl Integrator for AmbientOcclusion {
+    fn li(&self, ray: &Ray, scene: &Scene, sampler: &mut dyn Sampler) -> Color {
+        let ray = &Ray {
+            seed: sampler.get_seed(),
+            ..*ray
+        };
+        let mut hit_record = HitRecord::default();
+        if !scene.hit(ray, &mut hit_record) {
+            return scene.background_color(ray);
+        }
+
+        let pdf = CosinePdf::new(&hit_record.normal);
+        let mut unoccluded = 0;
+        for _ in 0..self.samples {
+            // cosine-distributed directions are unit length, so t is distance
+            let probe = Ray {
+                seed: sampler.get_seed(),
+                ..Ray::new(hit_record.p, pdf.generate(sampler))
+            };
+            let interval = Interval {
+                min: 0.001,
+                max: self.max_distance,
+            };
+            let mut probe_record = HitRecord::default();
+            if !scene.world.hit(&probe, interval, &mut probe_record) {
+                unoccluded += 1;
+            }
+        }
+
+        let visibility = unoccluded as f64 / u32::max(self.samples, 1) as f64;
+        Color::new(visibility, visibility, visibility)
+    }
+}
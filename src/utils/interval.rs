@@ -1,5 +1,6 @@
 use core::f64;
 
+#[derive(Copy, Clone)]
 pub struct Interval {
     pub min: f64,
     pub max: f64,
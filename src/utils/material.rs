@@ -1,13 +1,71 @@
-use super::{color::Color, hittable::HitRecord, ray::Ray, vec3::Vec3};
+use super::{
+    color::{luminance, Color},
+    hittable::HitRecord,
+    microfacet::{
+        fresnel_complex, fresnel_dielectric, generalized_half_vector, refract,
+        thin_film_reflectance, TrowbridgeReitz,
+    },
+    onb::Onb,
+    pdf::{
+        CoatedPdf, CosinePdf, HenyeyGreensteinPdf, LobeMixturePdf, MicrofacetDielectricPdf,
+        MicrofacetReflectionPdf, Pdf, SpherePdf,
+    },
+    ray::Ray,
+    sampler::{hash, Sampler},
+    spectrum::{Dispersion, SampledWavelengths},
+    texture::TextureParam,
+    vec3::Vec3,
+    voxel_grid::VoxelGrid,
+};
+
+// What a material does with an incoming ray. Specular materials leave `pdf`
+// empty and pick `skip_pdf_ray` themselves, with `attenuation` as its weight.
+// Everything else hands back the distribution it wants directions drawn from;
+// the integrator then weights each direction by eval / pdf, which lets it mix
+// in other strategies such as sampling the lights.
+#[derive(Default)]
+pub struct ScatterRecord {
+    pub attenuation: Color,
+    pub pdf: Option<Box<dyn Pdf>>,
+    pub skip_pdf_ray: Ray,
+    // set when the chosen direction depends on the ray's wavelength, so in
+    // spectral mode it is only right for the hero wavelength
+    pub dispersive: bool,
+}
 
 pub trait Material {
     fn scatter(
         &self,
         ray_in: &Ray,
         hit_record: &HitRecord,
-        attenuation: &mut Color,
-        scattered: &mut Ray,
+        scatter_record: &mut ScatterRecord,
+        sampler: &mut dyn Sampler,
     ) -> bool;
+
+    // BSDF times the cosine term for scattering from ray_in into `scattered`
+    fn eval(&self, _ray_in: &Ray, _hit_record: &HitRecord, _scattered: &Ray) -> Color {
+        Color::zero()
+    }
+
+    // solid angle density with which scatter() picks `scattered`
+    fn scattering_pdf(&self, _ray_in: &Ray, _hit_record: &HitRecord, _scattered: &Ray) -> f64 {
+        0.
+    }
+
+    fn emitted(&self, _ray_in: &Ray, _hit_record: &HitRecord) -> Color {
+        Color::zero()
+    }
+
+    // overall surface color for the albedo AOV and the denoiser
+    fn albedo(&self, _hit_record: &HitRecord) -> Color {
+        Color::new(1., 1., 1.)
+    }
+
+    // true when eval() and the attenuation from scatter() are already values
+    // at the ray's sampled wavelengths rather than RGB colors to upsample
+    fn spectral(&self, _ray_in: &Ray, _hit_record: &HitRecord) -> bool {
+        false
+    }
 }
 
 #[derive(Default)]
@@ -20,21 +78,147 @@ impl Material for Lambertian {
         &self,
         _ray_in: &Ray,
         hit_record: &HitRecord,
-        attenuation: &mut Color,
-        scattered: &mut Ray,
+        scatter_record: &mut ScatterRecord,
+        _sampler: &mut dyn Sampler,
+    ) -> bool {
+        scatter_record.attenuation = self.albedo;
+        scatter_record.pdf = Some(Box::new(CosinePdf::new(&hit_record.normal)));
+
+        true
+    }
+
+    fn eval(&self, ray_in: &Ray, hit_record: &HitRecord, scattered: &Ray) -> Color {
+        self.albedo * self.scattering_pdf(ray_in, hit_record, scattered)
+    }
+
+    fn scattering_pdf(&self, _ray_in: &Ray, hit_record: &HitRecord, scattered: &Ray) -> f64 {
+        let cos_theta = Vec3::dot(&hit_record.normal, &Vec3::unit_vector(&scattered.direction));
+        f64::max(0., cos_theta / std::f64::consts::PI)
+    }
+
+    fn albedo(&self, _hit_record: &HitRecord) -> Color {
+        self.albedo
+    }
+}
+
+// Oren-Nayar diffuse for surfaces made of tiny Lambertian facets, such as
+// clay, concrete or plaster. `sigma` is the standard deviation of the facet
+// slopes in degrees; rough surfaces look flatter than Lambertian ones and
+// scatter some light back towards where it came from. Zero is Lambertian.
+pub struct OrenNayar {
+    pub albedo: Color,
+    pub sigma: f64,
+}
+
+impl Material for OrenNayar {
+    fn scatter(
+        &self,
+        _ray_in: &Ray,
+        hit_record: &HitRecord,
+        scatter_record: &mut ScatterRecord,
+        _sampler: &mut dyn Sampler,
     ) -> bool {
-        let mut scatter_direction = hit_record.normal + Vec3::random_unit_vector();
-        if scatter_direction.near_zero() {
-            scatter_direction = hit_record.normal;
+        scatter_record.attenuation = self.albedo;
+        scatter_record.pdf = Some(Box::new(CosinePdf::new(&hit_record.normal)));
+
+        true
+    }
+
+    fn eval(&self, ray_in: &Ray, hit_record: &HitRecord, scattered: &Ray) -> Color {
+        let normal = hit_record.normal;
+        let wo = -Vec3::unit_vector(&ray_in.direction);
+        let wi = Vec3::unit_vector(&scattered.direction);
+        let cos_i = Vec3::dot(&normal, &wi);
+        let cos_o = Vec3::dot(&normal, &wo);
+        if cos_i <= 0. || cos_o <= 0. {
+            return Color::zero();
         }
 
-        *scattered = Ray {
-            origin: hit_record.p,
-            direction: scatter_direction,
+        // qualitative model from the paper, with the ratio of the larger to
+        // the smaller polar angle's sine and tangent
+        let sigma2 = self.sigma.to_radians().powi(2);
+        let a = 1. - sigma2 / (2. * (sigma2 + 0.33));
+        let b = 0.45 * sigma2 / (sigma2 + 0.09);
+
+        let sin_i = f64::max(0., 1. - cos_i * cos_i).sqrt();
+        let sin_o = f64::max(0., 1. - cos_o * cos_o).sqrt();
+        let cos_phi = if sin_i > 1e-4 && sin_o > 1e-4 {
+            let tangent_i = (wi - normal * cos_i) / sin_i;
+            let tangent_o = (wo - normal * cos_o) / sin_o;
+            f64::max(0., Vec3::dot(&tangent_i, &tangent_o))
+        } else {
+            0.
+        };
+        let (sin_alpha, tan_beta) = if cos_i > cos_o {
+            (sin_o, sin_i / cos_i)
+        } else {
+            (sin_i, sin_o / cos_o)
         };
-        *attenuation = self.albedo.clone();
 
-        return true;
+        self.albedo * ((a + b * cos_phi * sin_alpha * tan_beta) * cos_i / std::f64::consts::PI)
+    }
+
+    fn scattering_pdf(&self, _ray_in: &Ray, hit_record: &HitRecord, scattered: &Ray) -> f64 {
+        let cos_theta = Vec3::dot(&hit_record.normal, &Vec3::unit_vector(&scattered.direction));
+        f64::max(0., cos_theta / std::f64::consts::PI)
+    }
+
+    fn albedo(&self, _hit_record: &HitRecord) -> Color {
+        self.albedo
+    }
+}
+
+// Cloth: a diffuse base under a sheen from fibres that stand up from the
+// surface and catch light at grazing angles, so edges glow softly. The sheen
+// uses the Charlie distribution of Estevez and Kulla with Neubelt and
+// Pettineo's visibility term; `roughness` widens it from a thin rim at 0 to a
+// broad haze at 1.
+pub struct Velvet {
+    pub albedo: Color,
+    pub sheen: Color,
+    pub roughness: f64,
+}
+
+impl Material for Velvet {
+    fn scatter(
+        &self,
+        _ray_in: &Ray,
+        hit_record: &HitRecord,
+        scatter_record: &mut ScatterRecord,
+        _sampler: &mut dyn Sampler,
+    ) -> bool {
+        scatter_record.attenuation = self.albedo;
+        scatter_record.pdf = Some(Box::new(CosinePdf::new(&hit_record.normal)));
+
+        true
+    }
+
+    fn eval(&self, ray_in: &Ray, hit_record: &HitRecord, scattered: &Ray) -> Color {
+        let normal = hit_record.normal;
+        let wo = -Vec3::unit_vector(&ray_in.direction);
+        let wi = Vec3::unit_vector(&scattered.direction);
+        let cos_i = Vec3::dot(&normal, &wi);
+        let cos_o = Vec3::dot(&normal, &wo);
+        if cos_i <= 0. || cos_o <= 0. {
+            return Color::zero();
+        }
+
+        let alpha = self.roughness.clamp(0.1, 1.).powi(2);
+        let cos_h = Vec3::dot(&normal, &Vec3::unit_vector(&(wi + wo)));
+        let sin_h = f64::max(0., 1. - cos_h * cos_h).sqrt();
+        let d = (2. + 1. / alpha) * sin_h.powf(1. / alpha) / (2. * std::f64::consts::PI);
+        let visibility = 1. / (4. * (cos_i + cos_o - cos_i * cos_o));
+
+        (self.albedo / std::f64::consts::PI + self.sheen * (d * visibility)) * cos_i
+    }
+
+    fn scattering_pdf(&self, _ray_in: &Ray, hit_record: &HitRecord, scattered: &Ray) -> f64 {
+        let cos_theta = Vec3::dot(&hit_record.normal, &Vec3::unit_vector(&scattered.direction));
+        f64::max(0., cos_theta / std::f64::consts::PI)
+    }
+
+    fn albedo(&self, _hit_record: &HitRecord) -> Color {
+        self.albedo
     }
 }
 
@@ -58,23 +242,263 @@ impl Material for Metal {
         &self,
         ray_in: &Ray,
         hit_record: &HitRecord,
-        attenuation: &mut Color,
-        scattered: &mut Ray,
+        scatter_record: &mut ScatterRecord,
+        sampler: &mut dyn Sampler,
     ) -> bool {
         let mut reflected = Vec3::reflect(&ray_in.direction, &hit_record.normal);
-        reflected = Vec3::unit_vector(&reflected) + (Vec3::random_unit_vector() * self.fuzz);
-        *scattered = Ray {
-            origin: hit_record.p,
-            direction: reflected,
+        reflected = Vec3::unit_vector(&reflected) + (Vec3::random_unit_vector(sampler) * self.fuzz);
+        scatter_record.skip_pdf_ray = Ray::new(hit_record.p, reflected);
+        scatter_record.attenuation = self.albedo;
+        scatter_record.pdf = None;
+
+        Vec3::dot(&reflected, &hit_record.normal) > 0.
+    }
+
+    fn albedo(&self, _hit_record: &HitRecord) -> Color {
+        self.albedo
+    }
+}
+
+// Complex index of refraction eta + ik of a metal at the red, green and blue
+// primaries. The presets are fits to measured spectral data.
+#[derive(Copy, Clone)]
+pub struct ComplexIor {
+    pub eta: Color,
+    pub k: Color,
+}
+
+impl ComplexIor {
+    pub const GOLD: ComplexIor = ComplexIor {
+        eta: Color::new(0.143, 0.374, 1.442),
+        k: Color::new(3.983, 2.385, 1.603),
+    };
+    pub const COPPER: ComplexIor = ComplexIor {
+        eta: Color::new(0.200, 0.924, 1.102),
+        k: Color::new(3.912, 2.452, 2.142),
+    };
+    pub const ALUMINUM: ComplexIor = ComplexIor {
+        eta: Color::new(1.657, 0.880, 0.521),
+        k: Color::new(9.224, 6.270, 4.837),
+    };
+    pub const SILVER: ComplexIor = ComplexIor {
+        eta: Color::new(0.155, 0.117, 0.138),
+        k: Color::new(4.828, 3.122, 2.147),
+    };
+    pub const IRON: ComplexIor = ComplexIor {
+        eta: Color::new(2.911, 2.950, 2.585),
+        k: Color::new(3.089, 2.932, 2.767),
+    };
+
+    pub fn fresnel(&self, cos_theta_i: f64) -> Color {
+        Color::new(
+            fresnel_complex(cos_theta_i, self.eta.x, self.k.x),
+            fresnel_complex(cos_theta_i, self.eta.y, self.k.y),
+            fresnel_complex(cos_theta_i, self.eta.z, self.k.z),
+        )
+    }
+}
+
+// A transparent layer a few hundred nanometres thick on top of a surface,
+// like soap, oil on water or the coating on a lens. Light reflected off its
+// top and bottom interferes, tinting reflections with colors that shift with
+// the viewing angle and thickness.
+#[derive(Copy, Clone)]
+pub struct ThinFilm {
+    // in nanometres
+    pub thickness: f64,
+    pub refraction_index: f64,
+}
+
+impl ThinFilm {
+    // wavelengths standing in for the red, green and blue channels
+    const RGB_WAVELENGTHS: [f64; 3] = [630., 532., 465.];
+
+    // Reflectance over a base, seen from outside the film. `media` gives the
+    // index outside and the base's complex index (eta, k) at a wavelength.
+    // In RGB each channel is evaluated at a wavelength standing in for it;
+    // when rendering spectrally, at the path's sampled wavelengths.
+    pub fn reflectance(
+        &self,
+        cos_theta_i: f64,
+        wavelengths: Option<&SampledWavelengths>,
+        media: impl Fn(f64) -> (f64, f64, f64),
+    ) -> Color {
+        let at = |wavelength: f64| {
+            let (outside, eta, k) = media(wavelength);
+            thin_film_reflectance(
+                cos_theta_i,
+                outside,
+                self.refraction_index,
+                self.thickness,
+                eta,
+                k,
+                wavelength,
+            )
         };
-        *attenuation = self.albedo.clone();
+        let [first, second, third] = match wavelengths {
+            Some(wavelengths) => wavelengths.lambda,
+            None => Self::RGB_WAVELENGTHS,
+        };
+        Color::new(at(first), at(second), at(third))
+    }
+
+    // an RGB quantity read as a function of wavelength, linear between the
+    // channels' wavelengths and constant beyond them
+    fn channel_at(color: &Color, wavelength: f64) -> f64 {
+        let [red, green, blue] = Self::RGB_WAVELENGTHS;
+        if wavelength <= blue {
+            color.z
+        } else if wavelength <= green {
+            let t = (wavelength - blue) / (green - blue);
+            color.z + (color.y - color.z) * t
+        } else if wavelength <= red {
+            let t = (wavelength - green) / (red - green);
+            color.y + (color.x - color.y) * t
+        } else {
+            color.x
+        }
+    }
+}
+
+// Rough metal: a Trowbridge-Reitz (GGX) microfacet conductor with Smith
+// masking and shadowing and exact conductor Fresnel. Directions are drawn
+// from the visible normals, so only reflections the viewer can see get
+// sampled. Anisotropic roughness stretches highlights along the shading
+// frame's first tangent. A thin film on top adds oxide or heat-tint colors.
+pub struct GgxConductor {
+    pub ior: ComplexIor,
+    pub distribution: TrowbridgeReitz,
+    pub thin_film: Option<ThinFilm>,
+}
+
+impl GgxConductor {
+    pub fn new(ior: ComplexIor, roughness: f64) -> GgxConductor {
+        GgxConductor::anisotropic(ior, roughness, roughness)
+    }
+
+    pub fn anisotropic(ior: ComplexIor, roughness_u: f64, roughness_v: f64) -> GgxConductor {
+        GgxConductor {
+            ior,
+            distribution: TrowbridgeReitz::new(roughness_u, roughness_v),
+            thin_film: None,
+        }
+    }
+
+    fn fresnel(&self, cos_theta_i: f64, ray_in: &Ray) -> Color {
+        match self.thin_film {
+            Some(film) => film.reflectance(cos_theta_i, ray_in.wavelengths.as_ref(), |lambda| {
+                (
+                    1.,
+                    ThinFilm::channel_at(&self.ior.eta, lambda),
+                    ThinFilm::channel_at(&self.ior.k, lambda),
+                )
+            }),
+            None => self.ior.fresnel(cos_theta_i),
+        }
+    }
 
-        return Vec3::dot(&scattered.direction, &hit_record.normal) > 0.;
+    fn pdf(&self, ray_in: &Ray, hit_record: &HitRecord) -> MicrofacetReflectionPdf {
+        let frame = hit_record.shading_frame();
+        MicrofacetReflectionPdf {
+            frame,
+            wo: frame.to_local(&-Vec3::unit_vector(&ray_in.direction)),
+            distribution: self.distribution,
+        }
     }
 }
 
+impl Material for GgxConductor {
+    fn scatter(
+        &self,
+        ray_in: &Ray,
+        hit_record: &HitRecord,
+        scatter_record: &mut ScatterRecord,
+        _sampler: &mut dyn Sampler,
+    ) -> bool {
+        let pdf = self.pdf(ray_in, hit_record);
+        if pdf.wo.z <= 0. {
+            return false;
+        }
+
+        scatter_record.attenuation = self.fresnel(pdf.wo.z, ray_in);
+        if self.distribution.effectively_smooth() {
+            scatter_record.skip_pdf_ray = Ray::new(
+                hit_record.p,
+                Vec3::reflect(&ray_in.direction, &hit_record.normal),
+            );
+            scatter_record.pdf = None;
+        } else {
+            scatter_record.pdf = Some(Box::new(pdf));
+        }
+
+        true
+    }
+
+    fn eval(&self, ray_in: &Ray, hit_record: &HitRecord, scattered: &Ray) -> Color {
+        let pdf = self.pdf(ray_in, hit_record);
+        let wo = pdf.wo;
+        let wi = pdf.frame.to_local(&Vec3::unit_vector(&scattered.direction));
+        if wo.z <= 0. || wi.z <= 0. {
+            return Color::zero();
+        }
+
+        let wm = Vec3::unit_vector(&(wi + wo));
+        let fresnel = self.fresnel(Vec3::dot(&wo, &wm).abs(), ray_in);
+        let d = self.distribution.d(&wm);
+        let g = self.distribution.g(&wo, &wi);
+        // D F G / (4 cos_o cos_i), times cos_i
+        fresnel * (d * g / (4. * wo.z))
+    }
+
+    fn scattering_pdf(&self, ray_in: &Ray, hit_record: &HitRecord, scattered: &Ray) -> f64 {
+        self.pdf(ray_in, hit_record).value(&scattered.direction)
+    }
+
+    fn albedo(&self, _hit_record: &HitRecord) -> Color {
+        self.ior.fresnel(1.)
+    }
+
+    fn spectral(&self, ray_in: &Ray, _hit_record: &HitRecord) -> bool {
+        self.thin_film.is_some() && ray_in.wavelengths.is_some()
+    }
+}
+
+// Absorption coefficients (per unit length) that leave `color` of white light
+// after it travels `distance` through the medium; a friendlier way to pick a
+// tint than raw coefficients.
+pub fn absorption_from_color(color: Color, distance: f64) -> Color {
+    let coefficient = |c: f64| -f64::max(c, 1e-6).ln() / distance;
+    Color::new(
+        coefficient(color.x),
+        coefficient(color.y),
+        coefficient(color.z),
+    )
+}
+
+// Beer-Lambert falloff for light that has just crossed a dielectric's
+// interior. A ray hitting the back of the surface was travelling inside, so
+// its whole segment counts; this assumes nothing else sits inside the object.
+fn interior_transmittance(absorption: &Color, ray_in: &Ray, hit_record: &HitRecord) -> Color {
+    if hit_record.front_face {
+        return Color::new(1., 1., 1.);
+    }
+    let distance = hit_record.t * ray_in.direction.norm();
+    Color::new(
+        (-absorption.x * distance).exp(),
+        (-absorption.y * distance).exp(),
+        (-absorption.z * distance).exp(),
+    )
+}
+
 pub struct Dielectric {
     pub refraction_index: f64,
+    // per unit length inside the material; zero for clear glass
+    pub absorption: Color,
+    // replaces refraction_index when rendering spectrally
+    pub dispersion: Option<Dispersion>,
+    // a coating such as a soap film; a soap bubble is a film on a dielectric
+    // with a refraction_index of 1
+    pub thin_film: Option<ThinFilm>,
 }
 
 impl Material for Dielectric {
@@ -82,14 +506,27 @@ impl Material for Dielectric {
         &self,
         ray_in: &Ray,
         hit_record: &HitRecord,
-        attenuation: &mut Color,
-        scattered: &mut Ray,
+        scatter_record: &mut ScatterRecord,
+        sampler: &mut dyn Sampler,
     ) -> bool {
-        *attenuation = Color::new(1., 1., 1.);
+        scatter_record.attenuation = interior_transmittance(&self.absorption, ray_in, hit_record);
+        scatter_record.pdf = None;
+        let dispersion = match (self.dispersion, ray_in.wavelengths) {
+            (Some(dispersion), Some(_)) => Some(dispersion),
+            _ => None,
+        };
+        let index_at = |lambda: f64| match dispersion {
+            Some(dispersion) => dispersion.ior(lambda),
+            None => self.refraction_index,
+        };
+        let refraction_index = match ray_in.wavelengths {
+            Some(wavelengths) => index_at(wavelengths.hero()),
+            None => self.refraction_index,
+        };
         let ri = if hit_record.front_face {
-            1. / self.refraction_index
+            1. / refraction_index
         } else {
-            self.refraction_index
+            refraction_index
         };
 
         let unit_direction = Vec3::unit_vector(&ray_in.direction);
@@ -98,16 +535,56 @@ impl Material for Dielectric {
         let sin_theta = (1. - cos_theta * cos_theta).sqrt();
 
         let cannot_refract = ri * sin_theta > 1.;
-        let random_f64 = Vec3::random_coordinate(0., 1.);
-        let direction = if cannot_refract || Dielectric::reflectance(cos_theta, ri) > random_f64 {
+        let random_f64 = Vec3::random_coordinate(sampler, 0., 1.);
+        let reflect = match self.thin_film {
+            None => {
+                scatter_record.dispersive = dispersion.is_some();
+                cannot_refract || Dielectric::reflectance(cos_theta, ri) > random_f64
+            }
+            Some(film) => {
+                // every wavelength gets its own reflectance, so the result is
+                // spectral already; see spectral()
+                let wavelengths = ray_in.wavelengths.as_ref();
+                if let Some(wavelengths) = wavelengths {
+                    scatter_record.attenuation = wavelengths.upsample(&scatter_record.attenuation);
+                }
+                let reflectance = film.reflectance(cos_theta, wavelengths, |lambda| {
+                    let index = index_at(lambda);
+                    if hit_record.front_face {
+                        (1., index, 0.)
+                    } else {
+                        (index, 1., 0.)
+                    }
+                });
+
+                // pick by the average reflectance and weight each channel by
+                // its own
+                let probability = (reflectance.x + reflectance.y + reflectance.z) / 3.;
+                if cannot_refract || probability > random_f64 {
+                    scatter_record.attenuation *= reflectance / probability.max(1e-6);
+                    true
+                } else {
+                    scatter_record.attenuation *=
+                        (Color::new(1., 1., 1.) - reflectance) / (1. - probability);
+                    // only the refracted direction depends on the wavelength
+                    scatter_record.dispersive = dispersion.is_some();
+                    false
+                }
+            }
+        };
+        let direction = if reflect {
             Vec3::reflect(&unit_direction, &hit_record.normal)
         } else {
             Vec3::refract(&unit_direction, &hit_record.normal, ri)
         };
 
-        *scattered = Ray::new(hit_record.p, direction);
+        scatter_record.skip_pdf_ray = Ray::new(hit_record.p, direction);
         return true;
     }
+
+    fn spectral(&self, ray_in: &Ray, _hit_record: &HitRecord) -> bool {
+        self.thin_film.is_some() && ray_in.wavelengths.is_some()
+    }
 }
 
 impl Dielectric {
@@ -118,3 +595,945 @@ impl Dielectric {
         return r0 + (1. - r0) * f64::powf(1. - cosine, 5.);
     }
 }
+
+// Frosted glass: GGX microfacet reflection and transmission with exact
+// Fresnel. At zero roughness it becomes a smooth dielectric that still uses
+// exact Fresnel, unlike Dielectric's Schlick approximation. Radiance passing
+// into a denser medium is scaled by 1 / eta^2, as it is compressed into a
+// smaller solid angle; the factor cancels again on the way out.
+pub struct RoughDielectric {
+    pub refraction_index: f64,
+    pub distribution: TrowbridgeReitz,
+    // per unit length inside the material; zero for clear glass
+    pub absorption: Color,
+}
+
+impl RoughDielectric {
+    pub fn new(refraction_index: f64, roughness: f64) -> RoughDielectric {
+        RoughDielectric {
+            refraction_index,
+            distribution: TrowbridgeReitz::new(roughness, roughness),
+            absorption: Color::zero(),
+        }
+    }
+
+    fn pdf(&self, ray_in: &Ray, hit_record: &HitRecord) -> MicrofacetDielectricPdf {
+        // the normal always faces the incoming ray, so which side the ray
+        // comes from decides the relative index
+        let eta = if hit_record.front_face {
+            self.refraction_index
+        } else {
+            1. / self.refraction_index
+        };
+        let frame = hit_record.shading_frame();
+        MicrofacetDielectricPdf {
+            frame,
+            wo: frame.to_local(&-Vec3::unit_vector(&ray_in.direction)),
+            distribution: self.distribution,
+            eta,
+        }
+    }
+}
+
+impl Material for RoughDielectric {
+    fn scatter(
+        &self,
+        ray_in: &Ray,
+        hit_record: &HitRecord,
+        scatter_record: &mut ScatterRecord,
+        sampler: &mut dyn Sampler,
+    ) -> bool {
+        let pdf = self.pdf(ray_in, hit_record);
+        if pdf.wo.z <= 0. {
+            return false;
+        }
+        scatter_record.attenuation = interior_transmittance(&self.absorption, ray_in, hit_record);
+
+        if !self.distribution.effectively_smooth() {
+            scatter_record.pdf = Some(Box::new(pdf));
+            return true;
+        }
+
+        // smooth: pick between the mirror and refracted directions
+        let normal = Vec3::new(0., 0., 1.);
+        let reflectance = fresnel_dielectric(pdf.wo.z, pdf.eta);
+        let wi = match refract(&pdf.wo, &normal, pdf.eta) {
+            Some(refracted) if sampler.get_1d() >= reflectance => {
+                scatter_record.attenuation = scatter_record.attenuation / (pdf.eta * pdf.eta);
+                refracted
+            }
+            _ => Vec3::reflect(&-pdf.wo, &normal),
+        };
+        scatter_record.skip_pdf_ray = Ray::new(hit_record.p, pdf.frame.transform(&wi));
+        scatter_record.pdf = None;
+
+        true
+    }
+
+    fn eval(&self, ray_in: &Ray, hit_record: &HitRecord, scattered: &Ray) -> Color {
+        let pdf = self.pdf(ray_in, hit_record);
+        let wo = pdf.wo;
+        let wi = pdf.frame.to_local(&Vec3::unit_vector(&scattered.direction));
+        let (wm, etap) = match generalized_half_vector(&wo, &wi, pdf.eta) {
+            Some(half_vector) if wo.z > 0. => half_vector,
+            _ => return Color::zero(),
+        };
+
+        let wo_dot_wm = Vec3::dot(&wo, &wm);
+        let wi_dot_wm = Vec3::dot(&wi, &wm);
+        let reflectance = fresnel_dielectric(wo_dot_wm, pdf.eta);
+        let d = self.distribution.d(&wm);
+        let g = self.distribution.g(&wo, &wi);
+        // BSDF times |cos_i|
+        let value = if wi.z > 0. {
+            d * g * reflectance / (4. * wo.z)
+        } else {
+            let denominator = wi_dot_wm + wo_dot_wm / etap;
+            d * g * (1. - reflectance) * (wi_dot_wm * wo_dot_wm).abs()
+                / (denominator * denominator * wo.z)
+                / (etap * etap)
+        };
+        interior_transmittance(&self.absorption, ray_in, hit_record) * value
+    }
+
+    fn scattering_pdf(&self, ray_in: &Ray, hit_record: &HitRecord, scattered: &Ray) -> f64 {
+        self.pdf(ray_in, hit_record).value(&scattered.direction)
+    }
+}
+
+// Disney-style principled BSDF: one material whose familiar knobs, all in
+// [0, 1] and all texturable, cover plastics, metals, cloth, lacquer and glass.
+// It is a sum of lobes: a retro-reflective diffuse term plus sheen, a GGX
+// specular lobe whose color slides from dielectric to base color with
+// `metallic`, a fixed-index GGX clearcoat on top and GGX transmission. Each
+// lobe can be sampled and the mixture's density is exact, so it works with
+// light sampling and MIS.
+pub struct Principled<'a> {
+    pub base_color: TextureParam<'a>,
+    pub metallic: TextureParam<'a>,
+    // below 0.05 is clamped so every lobe stays sampleable
+    pub roughness: TextureParam<'a>,
+    // dielectric reflectance; 0.5 is 4%, the usual for glass and plastic
+    pub specular: TextureParam<'a>,
+    // tints dielectric reflections towards the base color
+    pub specular_tint: TextureParam<'a>,
+    // grazing-angle glow for cloth
+    pub sheen: TextureParam<'a>,
+    pub sheen_tint: TextureParam<'a>,
+    pub clearcoat: TextureParam<'a>,
+    // 0 is a satin coat, 1 a glossy one
+    pub clearcoat_gloss: TextureParam<'a>,
+    pub transmission: TextureParam<'a>,
+    // index of refraction for transmission
+    pub ior: f64,
+}
+
+impl Default for Principled<'_> {
+    fn default() -> Self {
+        Principled {
+            base_color: TextureParam::Constant(Color::new(0.8, 0.8, 0.8)),
+            metallic: TextureParam::scalar(0.),
+            roughness: TextureParam::scalar(0.5),
+            specular: TextureParam::scalar(0.5),
+            specular_tint: TextureParam::scalar(0.),
+            sheen: TextureParam::scalar(0.),
+            sheen_tint: TextureParam::scalar(0.5),
+            clearcoat: TextureParam::scalar(0.),
+            clearcoat_gloss: TextureParam::scalar(1.),
+            transmission: TextureParam::scalar(0.),
+            ior: 1.5,
+        }
+    }
+}
+
+// the principled parameters looked up at one hit, in the hit's local frame
+struct PrincipledLobes {
+    frame: Onb,
+    wo: Vec3,
+    eta: f64,
+    base_color: Color,
+    roughness: f64,
+    diffuse_weight: f64,
+    sheen_color: Color,
+    specular_f0: Color,
+    specular: TrowbridgeReitz,
+    clearcoat_weight: f64,
+    clearcoat: TrowbridgeReitz,
+    transmission_weight: f64,
+}
+
+fn schlick(f0: Color, cos_theta: f64) -> Color {
+    let weight = (1. - cos_theta.clamp(0., 1.)).powi(5);
+    f0 + (Color::new(1., 1., 1.) - f0) * weight
+}
+
+fn lerp(a: Color, b: Color, t: f64) -> Color {
+    a * (1. - t) + b * t
+}
+
+impl Principled<'_> {
+    fn lobes(&self, ray_in: &Ray, hit_record: &HitRecord) -> PrincipledLobes {
+        let white = Color::new(1., 1., 1.);
+        let base_color = self.base_color.color(hit_record);
+        let metallic = self.metallic.value(hit_record).clamp(0., 1.);
+        let roughness = self.roughness.value(hit_record).clamp(0.05, 1.);
+        let transmission = self.transmission.value(hit_record).clamp(0., 1.);
+
+        // the base color's hue at full brightness
+        let base_luminance = luminance(&base_color);
+        let tint = if base_luminance > 0. {
+            base_color / base_luminance
+        } else {
+            white
+        };
+        let dielectric_f0 = lerp(white, tint, self.specular_tint.value(hit_record))
+            * (0.08 * self.specular.value(hit_record));
+        let sheen_color = lerp(white, tint, self.sheen_tint.value(hit_record))
+            * (self.sheen.value(hit_record) * (1. - metallic));
+        let clearcoat_roughness = 0.3 + (0.05 - 0.3) * self.clearcoat_gloss.value(hit_record);
+
+        let frame = hit_record.shading_frame();
+        PrincipledLobes {
+            frame,
+            wo: frame.to_local(&-Vec3::unit_vector(&ray_in.direction)),
+            eta: if hit_record.front_face {
+                self.ior
+            } else {
+                1. / self.ior
+            },
+            base_color,
+            roughness,
+            diffuse_weight: (1. - metallic) * (1. - transmission),
+            sheen_color,
+            specular_f0: lerp(dielectric_f0, base_color, metallic),
+            specular: TrowbridgeReitz::new(roughness, roughness),
+            clearcoat_weight: 0.25 * self.clearcoat.value(hit_record),
+            clearcoat: TrowbridgeReitz::new(clearcoat_roughness, clearcoat_roughness),
+            transmission_weight: (1. - metallic) * transmission,
+        }
+    }
+
+    fn pdf(&self, lobes: &PrincipledLobes) -> LobeMixturePdf {
+        let wo = lobes.wo;
+        let reflection = |distribution: TrowbridgeReitz| {
+            Box::new(MicrofacetReflectionPdf {
+                frame: lobes.frame,
+                wo,
+                distribution,
+            })
+        };
+
+        // pick lobes roughly in proportion to how much light they reflect
+        let mut pdf = LobeMixturePdf::default();
+        pdf.add(
+            lobes.diffuse_weight * luminance(&lobes.base_color) + luminance(&lobes.sheen_color),
+            Box::new(CosinePdf::new(&lobes.frame.w)),
+        );
+        pdf.add(
+            luminance(&schlick(lobes.specular_f0, wo.z)),
+            reflection(lobes.specular),
+        );
+        pdf.add(
+            lobes.clearcoat_weight * schlick(Color::new(0.04, 0.04, 0.04), wo.z).x,
+            reflection(lobes.clearcoat),
+        );
+        pdf.add(
+            lobes.transmission_weight,
+            Box::new(MicrofacetDielectricPdf {
+                frame: lobes.frame,
+                wo,
+                distribution: lobes.specular,
+                eta: lobes.eta,
+            }),
+        );
+        pdf
+    }
+}
+
+impl Material for Principled<'_> {
+    fn scatter(
+        &self,
+        ray_in: &Ray,
+        hit_record: &HitRecord,
+        scatter_record: &mut ScatterRecord,
+        _sampler: &mut dyn Sampler,
+    ) -> bool {
+        let lobes = self.lobes(ray_in, hit_record);
+        if lobes.wo.z <= 0. {
+            return false;
+        }
+        let pdf = self.pdf(&lobes);
+        if pdf.is_empty() {
+            return false;
+        }
+
+        scatter_record.attenuation = lobes.base_color;
+        scatter_record.pdf = Some(Box::new(pdf));
+        true
+    }
+
+    fn eval(&self, ray_in: &Ray, hit_record: &HitRecord, scattered: &Ray) -> Color {
+        let lobes = self.lobes(ray_in, hit_record);
+        let wo = lobes.wo;
+        let wi = lobes
+            .frame
+            .to_local(&Vec3::unit_vector(&scattered.direction));
+        if wo.z <= 0. || wi.z == 0. {
+            return Color::zero();
+        }
+
+        if wi.z < 0. {
+            // refraction into the surface, tinted by the square root of the
+            // base color so a ray going in and out again picks up all of it
+            let (wm, etap) = match generalized_half_vector(&wo, &wi, lobes.eta) {
+                Some(half_vector) => half_vector,
+                None => return Color::zero(),
+            };
+            let wo_dot_wm = Vec3::dot(&wo, &wm);
+            let wi_dot_wm = Vec3::dot(&wi, &wm);
+            let denominator = wi_dot_wm + wo_dot_wm / etap;
+            let transmitted = lobes.specular.d(&wm)
+                * lobes.specular.g(&wo, &wi)
+                * (1. - fresnel_dielectric(wo_dot_wm, lobes.eta))
+                * (wi_dot_wm * wo_dot_wm).abs()
+                / (denominator * denominator * wo.z);
+            let tint = Color::new(
+                lobes.base_color.x.sqrt(),
+                lobes.base_color.y.sqrt(),
+                lobes.base_color.z.sqrt(),
+            );
+            return tint * (transmitted * lobes.transmission_weight);
+        }
+
+        let wh = Vec3::unit_vector(&(wi + wo));
+        let cos_d = Vec3::dot(&wi, &wh);
+
+        // Disney diffuse: a Lambertian that gets brighter towards grazing
+        // angles on rough surfaces and darker on smooth ones
+        let fd90 = 0.5 + 2. * lobes.roughness * cos_d * cos_d;
+        let retro = |cos: f64| 1. + (fd90 - 1.) * (1. - cos).powi(5);
+        let diffuse = lobes.base_color
+            * (lobes.diffuse_weight * retro(wi.z) * retro(wo.z) / std::f64::consts::PI);
+        let sheen = lobes.sheen_color * (1. - cos_d).powi(5);
+
+        // both specular lobes are D F G / (4 cos_o cos_i)
+        let specular = schlick(lobes.specular_f0, cos_d)
+            * (lobes.specular.d(&wh) * lobes.specular.g(&wo, &wi) / (4. * wo.z * wi.z));
+        let clearcoat = lobes.clearcoat_weight
+            * schlick(Color::new(0.04, 0.04, 0.04), cos_d).x
+            * lobes.clearcoat.d(&wh)
+            * lobes.clearcoat.g(&wo, &wi)
+            / (4. * wo.z * wi.z);
+
+        (diffuse + sheen + specular + Color::new(clearcoat, clearcoat, clearcoat)) * wi.z
+    }
+
+    fn scattering_pdf(&self, ray_in: &Ray, hit_record: &HitRecord, scattered: &Ray) -> f64 {
+        let lobes = self.lobes(ray_in, hit_record);
+        if lobes.wo.z <= 0. {
+            return 0.;
+        }
+        let pdf = self.pdf(&lobes);
+        if pdf.is_empty() {
+            return 0.;
+        }
+        pdf.value(&scattered.direction)
+    }
+
+    fn albedo(&self, hit_record: &HitRecord) -> Color {
+        self.base_color.color(hit_record)
+    }
+}
+
+// Blend of two materials, `amount` of the way from `first` to `second`. At
+// each hit one of them is picked with that probability and does all the
+// scattering. The pick is a hash of the incoming ray's seed and the hit
+// point, so eval and scattering_pdf, which get no sampler, agree with scatter
+// on it.
+pub struct Mix<'a> {
+    pub first: &'a dyn Material,
+    pub second: &'a dyn Material,
+    pub amount: TextureParam<'a>,
+}
+
+impl<'a> Mix<'a> {
+    fn choose(&self, ray_in: &Ray, hit_record: &HitRecord) -> &'a dyn Material {
+        let amount = self.amount.value(hit_record);
+        if amount <= 0. {
+            return self.first;
+        }
+        if amount >= 1. {
+            return self.second;
+        }
+
+        let (p, d) = (hit_record.p, ray_in.direction);
+        let bits = hash(&[
+            ray_in.seed,
+            p.x.to_bits(),
+            p.y.to_bits(),
+            p.z.to_bits(),
+            d.x.to_bits(),
+            d.y.to_bits(),
+            d.z.to_bits(),
+        ]);
+        if ((bits >> 11) as f64 / (1u64 << 53) as f64) < amount {
+            self.second
+        } else {
+            self.first
+        }
+    }
+}
+
+impl Material for Mix<'_> {
+    fn scatter(
+        &self,
+        ray_in: &Ray,
+        hit_record: &HitRecord,
+        scatter_record: &mut ScatterRecord,
+        sampler: &mut dyn Sampler,
+    ) -> bool {
+        self.choose(ray_in, hit_record)
+            .scatter(ray_in, hit_record, scatter_record, sampler)
+    }
+
+    fn eval(&self, ray_in: &Ray, hit_record: &HitRecord, scattered: &Ray) -> Color {
+        self.choose(ray_in, hit_record)
+            .eval(ray_in, hit_record, scattered)
+    }
+
+    fn scattering_pdf(&self, ray_in: &Ray, hit_record: &HitRecord, scattered: &Ray) -> f64 {
+        self.choose(ray_in, hit_record)
+            .scattering_pdf(ray_in, hit_record, scattered)
+    }
+
+    fn emitted(&self, ray_in: &Ray, hit_record: &HitRecord) -> Color {
+        self.choose(ray_in, hit_record).emitted(ray_in, hit_record)
+    }
+
+    fn spectral(&self, ray_in: &Ray, hit_record: &HitRecord) -> bool {
+        self.choose(ray_in, hit_record).spectral(ray_in, hit_record)
+    }
+
+    fn albedo(&self, hit_record: &HitRecord) -> Color {
+        let amount = self.amount.value(hit_record).clamp(0., 1.);
+        lerp(
+            self.first.albedo(hit_record),
+            self.second.albedo(hit_record),
+            amount,
+        )
+    }
+}
+
+// A thin, smooth dielectric coat such as varnish or lacquer over any other
+// material. Light either mirrors off the coat with its Fresnel reflectance,
+// or refracts in, scatters off `base` and refracts back out, losing some to
+// `absorption` on the way through the coat. Light the coat reflects back
+// down onto the base is dropped, so coated diffuse surfaces come out somewhat
+// darker than the bare base.
+pub struct Coated<'a> {
+    pub base: &'a dyn Material,
+    pub refraction_index: f64,
+    // optical depth of the coat measured straight through it; zero for a
+    // clear coat
+    pub absorption: Color,
+}
+
+impl<'a> Coated<'a> {
+    // a coat is never less dense than the air around it; indices below 1
+    // are raised to 1
+    pub fn new(base: &'a dyn Material, refraction_index: f64) -> Coated<'a> {
+        Coated {
+            base,
+            refraction_index: f64::max(refraction_index, 1.),
+            absorption: Color::zero(),
+        }
+    }
+
+    // direction under the coat for one above it, both pointing away from
+    // the surface; None only for an index below 1, where light can be
+    // totally reflected on the way in
+    fn inside(&self, direction: &Vec3, normal: &Vec3) -> Option<Vec3> {
+        refract(direction, normal, self.refraction_index).map(|refracted| -refracted)
+    }
+
+    // Beer-Lambert falloff through the coat along a direction under it
+    fn transmittance(&self, cos_inside: f64) -> Color {
+        let depth = 1. / f64::max(cos_inside.abs(), 1e-3);
+        Color::new(
+            (-self.absorption.x * depth).exp(),
+            (-self.absorption.y * depth).exp(),
+            (-self.absorption.z * depth).exp(),
+        )
+    }
+
+    // the incoming ray as the base sees it, refracted by the coat, together
+    // with the cosines of the outside and inside directions towards it
+    fn ray_inside(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<(Ray, f64, f64)> {
+        let wo = -Vec3::unit_vector(&ray_in.direction);
+        let inside = self.inside(&wo, &hit_record.normal)?;
+        let ray = Ray::new(hit_record.p + inside, -inside);
+        Some((
+            ray,
+            Vec3::dot(&wo, &hit_record.normal),
+            Vec3::dot(&inside, &hit_record.normal),
+        ))
+    }
+}
+
+impl Material for Coated<'_> {
+    fn scatter(
+        &self,
+        ray_in: &Ray,
+        hit_record: &HitRecord,
+        scatter_record: &mut ScatterRecord,
+        sampler: &mut dyn Sampler,
+    ) -> bool {
+        let normal = hit_record.normal;
+        let (ray_inside, cos_o, cos_o_inside) = match self.ray_inside(ray_in, hit_record) {
+            Some(inside) if inside.1 > 0. => inside,
+            _ => return false,
+        };
+
+        // pick the mirror reflection with the coat's reflectance, which
+        // cancels against it in the weight
+        let reflectance = fresnel_dielectric(cos_o, self.refraction_index);
+        if sampler.get_1d() < reflectance {
+            scatter_record.attenuation = Color::new(1., 1., 1.);
+            scatter_record.pdf = None;
+            scatter_record.skip_pdf_ray = Ray::new(
+                hit_record.p,
+                Vec3::reflect(&Vec3::unit_vector(&ray_in.direction), &normal),
+            );
+            return true;
+        }
+
+        let mut base_record = ScatterRecord::default();
+        if !self
+            .base
+            .scatter(&ray_inside, hit_record, &mut base_record, sampler)
+        {
+            return false;
+        }
+
+        match base_record.pdf {
+            Some(pdf) => {
+                scatter_record.attenuation = base_record.attenuation;
+                scatter_record.pdf = Some(Box::new(CoatedPdf {
+                    normal,
+                    eta: self.refraction_index,
+                    weight: 1. - reflectance,
+                    base: pdf,
+                }));
+            }
+            None => {
+                // a specular base: follow its ray back out through the coat
+                let direction = Vec3::unit_vector(&base_record.skip_pdf_ray.direction);
+                let cos_i_inside = Vec3::dot(&direction, &normal);
+                let mut attenuation = base_record.attenuation * self.transmittance(cos_o_inside);
+                let direction = if cos_i_inside > 0. {
+                    let outside = match refract(&direction, &normal, 1. / self.refraction_index) {
+                        Some(refracted) => -refracted,
+                        None => return false,
+                    };
+                    let cos_i = Vec3::dot(&outside, &normal);
+                    attenuation = attenuation
+                        * self.transmittance(cos_i_inside)
+                        * (1. - fresnel_dielectric(cos_i, self.refraction_index));
+                    outside
+                } else {
+                    direction
+                };
+                scatter_record.attenuation = attenuation;
+                scatter_record.pdf = None;
+                scatter_record.skip_pdf_ray = Ray::new(base_record.skip_pdf_ray.origin, direction);
+            }
+        }
+        true
+    }
+
+    fn eval(&self, ray_in: &Ray, hit_record: &HitRecord, scattered: &Ray) -> Color {
+        let normal = hit_record.normal;
+        let (ray_inside, cos_o, cos_o_inside) = match self.ray_inside(ray_in, hit_record) {
+            Some(inside) if inside.1 > 0. => inside,
+            _ => return Color::zero(),
+        };
+        let entering = self.transmittance(cos_o_inside)
+            * (1. - fresnel_dielectric(cos_o, self.refraction_index));
+
+        let wi = Vec3::unit_vector(&scattered.direction);
+        let cos_i = Vec3::dot(&wi, &normal);
+        if cos_i <= 0. {
+            // through the coat and on into the surface
+            return self.base.eval(&ray_inside, hit_record, scattered) * entering;
+        }
+
+        let wi_inside = match self.inside(&wi, &normal) {
+            Some(inside) => inside,
+            None => return Color::zero(),
+        };
+        let cos_i_inside = Vec3::dot(&wi_inside, &normal);
+        let scattered_inside = Ray::new(scattered.origin, wi_inside);
+        let leaving = self.transmittance(cos_i_inside)
+            * (1. - fresnel_dielectric(cos_i, self.refraction_index));
+
+        // the base's cosine is inside the coat; swap it for the outside one
+        // and account for refraction squeezing solid angle
+        let eta2 = self.refraction_index * self.refraction_index;
+        self.base.eval(&ray_inside, hit_record, &scattered_inside)
+            * entering
+            * leaving
+            * (cos_i / (eta2 * cos_i_inside))
+    }
+
+    fn scattering_pdf(&self, ray_in: &Ray, hit_record: &HitRecord, scattered: &Ray) -> f64 {
+        let normal = hit_record.normal;
+        let (ray_inside, cos_o, _) = match self.ray_inside(ray_in, hit_record) {
+            Some(inside) if inside.1 > 0. => inside,
+            _ => return 0.,
+        };
+        let weight = 1. - fresnel_dielectric(cos_o, self.refraction_index);
+
+        let wi = Vec3::unit_vector(&scattered.direction);
+        let cos_i = Vec3::dot(&wi, &normal);
+        if cos_i <= 0. {
+            return weight * self.base.scattering_pdf(&ray_inside, hit_record, scattered);
+        }
+
+        let wi_inside = match self.inside(&wi, &normal) {
+            Some(inside) => inside,
+            None => return 0.,
+        };
+        let cos_i_inside = Vec3::dot(&wi_inside, &normal);
+        let scattered_inside = Ray::new(scattered.origin, wi_inside);
+        weight
+            * self
+                .base
+                .scattering_pdf(&ray_inside, hit_record, &scattered_inside)
+            * cos_i
+            / (self.refraction_index * self.refraction_index * cos_i_inside)
+    }
+
+    fn emitted(&self, ray_in: &Ray, hit_record: &HitRecord) -> Color {
+        self.base.emitted(ray_in, hit_record)
+    }
+
+    fn albedo(&self, hit_record: &HitRecord) -> Color {
+        self.base.albedo(hit_record)
+    }
+}
+
+// phase function for participating media: scatters uniformly in all directions,
+// optionally glowing with an emission that can be modulated by a voxel grid
+// (e.g. temperature for fire). Only the absorbed 1 - albedo of each collision
+// emits, so `emission` is the radiance of a medium thick enough to be opaque
+// whatever its albedo.
+pub struct Isotropic<'a> {
+    pub albedo: Color,
+    pub emission: Color,
+    pub emission_grid: Option<&'a VoxelGrid>,
+}
+
+impl<'a> Isotropic<'a> {
+    pub fn new(albedo: Color) -> Isotropic<'a> {
+        Isotropic {
+            albedo,
+            emission: Color::zero(),
+            emission_grid: None,
+        }
+    }
+
+    pub fn emissive(albedo: Color, emission: Color, emission_grid: &'a VoxelGrid) -> Isotropic<'a> {
+        Isotropic {
+            albedo,
+            emission,
+            emission_grid: Some(emission_grid),
+        }
+    }
+}
+
+impl Material for Isotropic<'_> {
+    fn scatter(
+        &self,
+        _ray_in: &Ray,
+        _hit_record: &HitRecord,
+        scatter_record: &mut ScatterRecord,
+        _sampler: &mut dyn Sampler,
+    ) -> bool {
+        scatter_record.attenuation = self.albedo;
+        scatter_record.pdf = Some(Box::new(SpherePdf));
+
+        true
+    }
+
+    fn eval(&self, ray_in: &Ray, hit_record: &HitRecord, scattered: &Ray) -> Color {
+        self.albedo * self.scattering_pdf(ray_in, hit_record, scattered)
+    }
+
+    fn scattering_pdf(&self, _ray_in: &Ray, _hit_record: &HitRecord, _scattered: &Ray) -> f64 {
+        1. / (4. * std::f64::consts::PI)
+    }
+
+    fn emitted(&self, _ray_in: &Ray, hit_record: &HitRecord) -> Color {
+        // collisions are found in proportion to sigma_t but only sigma_a
+        // emits
+        let absorbed = self.emission * (Color::new(1., 1., 1.) - self.albedo);
+        match self.emission_grid {
+            Some(grid) => absorbed * grid.lookup(&hit_record.p),
+            None => absorbed,
+        }
+    }
+
+    fn albedo(&self, _hit_record: &HitRecord) -> Color {
+        self.albedo
+    }
+}
+
+// The inside of a translucent object such as skin, wax, marble or milk, for
+// volume::Subsurface to random-walk through. Coefficients are per unit length
+// and may differ per channel: the walk steps at the largest extinction and
+// each tentative collision either scatters, off a Henyey-Greenstein phase
+// function with `anisotropy` as its g, or passes straight through as a null
+// collision. Weighting each channel by its own coefficients (weighted delta
+// tracking) keeps every channel unbiased.
+pub struct SubsurfaceMedium {
+    pub sigma_s: Color,
+    pub sigma_a: Color,
+    pub anisotropy: f64,
+}
+
+impl SubsurfaceMedium {
+    // a medium that scatters `albedo` of the light at each collision and
+    // travels `mean_free_path` between collisions on average
+    pub fn from_albedo(albedo: Color, mean_free_path: f64, anisotropy: f64) -> SubsurfaceMedium {
+        let sigma_t = 1. / mean_free_path;
+        SubsurfaceMedium {
+            sigma_s: albedo * sigma_t,
+            sigma_a: (Color::new(1., 1., 1.) - albedo) * sigma_t,
+            anisotropy,
+        }
+    }
+
+    // the extinction tentative collisions are sampled at
+    pub fn majorant(&self) -> f64 {
+        (self.sigma_s + self.sigma_a).max_component()
+    }
+
+    fn sigma_n(&self) -> Color {
+        let majorant = self.majorant();
+        Color::new(majorant, majorant, majorant) - self.sigma_s - self.sigma_a
+    }
+
+    // probability of treating a tentative collision as real scattering
+    fn scatter_probability(&self) -> f64 {
+        let scattering = self.sigma_s.x + self.sigma_s.y + self.sigma_s.z;
+        let null = self.sigma_n();
+        let total = scattering + null.x + null.y + null.z;
+        if total <= 0. {
+            return 1.;
+        }
+        scattering / total
+    }
+}
+
+impl Material for SubsurfaceMedium {
+    fn scatter(
+        &self,
+        ray_in: &Ray,
+        hit_record: &HitRecord,
+        scatter_record: &mut ScatterRecord,
+        sampler: &mut dyn Sampler,
+    ) -> bool {
+        let probability = self.scatter_probability();
+        if probability <= 0. {
+            // nothing scatters, so the walk can only be absorbed
+            return false;
+        }
+        if sampler.get_1d() >= probability {
+            scatter_record.attenuation = self.sigma_n() / (self.majorant() * (1. - probability));
+            scatter_record.pdf = None;
+            scatter_record.skip_pdf_ray = Ray::new(hit_record.p, ray_in.direction);
+            return true;
+        }
+
+        scatter_record.attenuation = self.sigma_s / (self.majorant() * probability);
+        scatter_record.pdf = Some(Box::new(HenyeyGreensteinPdf::new(
+            &ray_in.direction,
+            self.anisotropy,
+        )));
+        true
+    }
+
+    fn eval(&self, ray_in: &Ray, hit_record: &HitRecord, scattered: &Ray) -> Color {
+        self.sigma_s / (self.majorant() * self.scatter_probability())
+            * self.scattering_pdf(ray_in, hit_record, scattered)
+    }
+
+    fn scattering_pdf(&self, ray_in: &Ray, _hit_record: &HitRecord, scattered: &Ray) -> f64 {
+        HenyeyGreensteinPdf::new(&ray_in.direction, self.anisotropy).value(&scattered.direction)
+    }
+
+    fn albedo(&self, _hit_record: &HitRecord) -> Color {
+        let sigma_t = self.sigma_s + self.sigma_a;
+        Color::new(
+            self.sigma_s.x / sigma_t.x.max(1e-12),
+            self.sigma_s.y / sigma_t.y.max(1e-12),
+            self.sigma_s.z / sigma_t.z.max(1e-12),
+        )
+    }
+}
+
+// emits light from its front face and absorbs everything that hits it
+pub struct DiffuseLight {
+    pub emit: Color,
+}
+
+impl Material for DiffuseLight {
+    fn scatter(
+        &self,
+        _ray_in: &Ray,
+        _hit_record: &HitRecord,
+        _scatter_record: &mut ScatterRecord,
+        _sampler: &mut dyn Sampler,
+    ) -> bool {
+        false
+    }
+
+    fn emitted(&self, _ray_in: &Ray, hit_record: &HitRecord) -> Color {
+        if !hit_record.front_face {
+            return Color::zero();
+        }
+        self.emit
+    }
+
+    fn albedo(&self, _hit_record: &HitRecord) -> Color {
+        Color::zero()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::{
+        color::Color,
+        hittable::HitRecord,
+        material::{
+            Coated, ComplexIor, GgxConductor, Lambertian, Material, Mix, OrenNayar, ScatterRecord,
+            SubsurfaceMedium,
+        },
+        ray::Ray,
+        sampler::IndependentSampler,
+        texture::TextureParam,
+        vec3::Vec3,
+    };
+
+    fn hit_facing_up() -> HitRecord<'static> {
+        let mut hit_record = HitRecord::default();
+        hit_record.normal = Vec3::new(0., 0., 1.);
+        hit_record.geometric_normal = hit_record.normal;
+        hit_record.front_face = true;
+        hit_record
+    }
+
+    // eval and scattering_pdf of two materials agree for a spread of
+    // directions above and below the surface
+    fn assert_same_bsdf(a: &dyn Material, b: &dyn Material, hit_record: &HitRecord) {
+        let ray_in = Ray::new(Vec3::new(0.6, -0.2, 1.), Vec3::new(-0.6, 0.2, -1.));
+        for direction in [
+            Vec3::new(-0.5, 0.3, 0.8),
+            Vec3::new(-0.6, 0.2, 1.),
+            Vec3::new(0.1, 0.9, 0.2),
+            Vec3::new(0.3, 0.1, -0.9),
+        ] {
+            let scattered = Ray::new(Vec3::zero(), direction);
+            let (eval_a, eval_b) = (
+                a.eval(&ray_in, hit_record, &scattered),
+                b.eval(&ray_in, hit_record, &scattered),
+            );
+            assert!((eval_a - eval_b).norm() <= 1e-9 * (1. + eval_a.norm()));
+            let (pdf_a, pdf_b) = (
+                a.scattering_pdf(&ray_in, hit_record, &scattered),
+                b.scattering_pdf(&ray_in, hit_record, &scattered),
+            );
+            assert!((pdf_a - pdf_b).abs() <= 1e-9 * (1. + pdf_a.abs()));
+        }
+    }
+
+    #[test]
+    fn test_oren_nayar_smooth_is_lambertian() {
+        let albedo = Color::new(0.2, 0.5, 0.8);
+        let lambertian = Lambertian { albedo };
+        let oren_nayar = OrenNayar { albedo, sigma: 0. };
+
+        let mut hit_record = HitRecord::default();
+        hit_record.normal = Vec3::new(0., 0., 1.);
+        let ray_in = Ray::new(Vec3::new(1., 0., 1.), Vec3::new(-1., 0., -1.));
+        let scattered = Ray::new(Vec3::zero(), Vec3::new(-0.5, 0.3, 0.8));
+
+        let expected = lambertian.eval(&ray_in, &hit_record, &scattered);
+        let actual = oren_nayar.eval(&ray_in, &hit_record, &scattered);
+        assert!((expected - actual).norm() < 1e-12);
+    }
+    #[test]
+    fn test_mix_endpoints() {
+        let lambertian = Lambertian {
+            albedo: Color::new(0.2, 0.5, 0.8),
+        };
+        let gold = GgxConductor::new(ComplexIor::GOLD, 0.3);
+        let mix = |amount: f64| Mix {
+            first: &lambertian,
+            second: &gold,
+            amount: TextureParam::scalar(amount),
+        };
+        let hit_record = hit_facing_up();
+
+        assert_same_bsdf(&mix(0.), &lambertian, &hit_record);
+        assert_same_bsdf(&mix(1.), &gold, &hit_record);
+    }
+
+    #[test]
+    fn test_index_matched_coat_is_invisible() {
+        // with the coat's index equal to the air's there is no interface
+        let gold = GgxConductor::new(ComplexIor::GOLD, 0.3);
+        let coated = Coated::new(&gold, 1.);
+        assert_same_bsdf(&coated, &gold, &hit_facing_up());
+    }
+
+    #[test]
+    fn test_subsurface_weights() {
+        // weighted delta tracking: a tentative collision scatters with
+        // weight sigma_s / majorant on average and passes on with
+        // sigma_n / majorant, so following null collisions until one
+        // scatters leaves sigma_s / sigma_t per channel
+        let medium = SubsurfaceMedium {
+            sigma_s: Color::new(3., 1.5, 0.4),
+            sigma_a: Color::new(1., 0.5, 2.),
+            anisotropy: 0.3,
+        };
+        let ray_in = Ray::new(Vec3::zero(), Vec3::new(0., 0., 1.));
+        let hit_record = HitRecord::default();
+        let mut sampler = IndependentSampler::new(5);
+
+        let walks = 100000;
+        let mut total = Color::zero();
+        for _ in 0..walks {
+            let mut weight = Color::new(1., 1., 1.);
+            loop {
+                let mut scatter_record = ScatterRecord::default();
+                assert!(medium.scatter(&ray_in, &hit_record, &mut scatter_record, &mut sampler));
+                weight *= scatter_record.attenuation;
+                if scatter_record.pdf.is_some() {
+                    break;
+                }
+            }
+            total += weight;
+        }
+        let average = total / walks as f64;
+
+        let sigma_t = medium.sigma_s + medium.sigma_a;
+        let expected = Color::new(
+            medium.sigma_s.x / sigma_t.x,
+            medium.sigma_s.y / sigma_t.y,
+            medium.sigma_s.z / sigma_t.z,
+        );
+        assert!((average - expected).norm() < 0.01);
+    }
+}
@@ -0,0 +1,345 @@
+use std::f64::consts::PI;
+
+use super::vec3::Vec3;
+
+// Trowbridge-Reitz (GGX) distribution of microfacet normals. Everything here
+// works in a local shading frame with the surface normal along +z and the
+// two roughness directions along x and y.
+#[derive(Copy, Clone)]
+pub struct TrowbridgeReitz {
+    pub alpha_x: f64,
+    pub alpha_y: f64,
+}
+
+impl TrowbridgeReitz {
+    // perceptual roughness in [0, 1] is squared into alpha, which makes
+    // roughness changes look roughly even
+    pub fn new(roughness_x: f64, roughness_y: f64) -> TrowbridgeReitz {
+        TrowbridgeReitz {
+            alpha_x: roughness_x.clamp(0., 1.).powi(2),
+            alpha_y: roughness_y.clamp(0., 1.).powi(2),
+        }
+    }
+
+    // too smooth to sample reliably; treat as a perfect mirror instead
+    pub fn effectively_smooth(&self) -> bool {
+        f64::max(self.alpha_x, self.alpha_y) < 1e-3
+    }
+
+    fn tan2_theta(w: &Vec3) -> f64 {
+        let cos2 = w.z * w.z;
+        f64::max(0., 1. - cos2) / cos2
+    }
+
+    // squared cos and sin of the azimuth of `w`
+    fn cos2_sin2_phi(w: &Vec3) -> (f64, f64) {
+        let sin2_theta = f64::max(0., 1. - w.z * w.z);
+        if sin2_theta <= 0. {
+            return (1., 0.);
+        }
+        let cos2 = (w.x * w.x / sin2_theta).clamp(0., 1.);
+        (cos2, 1. - cos2)
+    }
+
+    // density of microfacet normal `wm` per unit projected area
+    pub fn d(&self, wm: &Vec3) -> f64 {
+        let tan2 = Self::tan2_theta(wm);
+        if !tan2.is_finite() {
+            return 0.;
+        }
+        let cos4 = wm.z.powi(4);
+        if cos4 < 1e-16 {
+            return 0.;
+        }
+        let (cos2_phi, sin2_phi) = Self::cos2_sin2_phi(wm);
+        let e = tan2
+            * (cos2_phi / (self.alpha_x * self.alpha_x) + sin2_phi / (self.alpha_y * self.alpha_y));
+        1. / (PI * self.alpha_x * self.alpha_y * cos4 * (1. + e) * (1. + e))
+    }
+
+    // Smith's auxiliary function for the height-correlated masking term
+    fn lambda(&self, w: &Vec3) -> f64 {
+        let tan2 = Self::tan2_theta(w);
+        if !tan2.is_finite() {
+            return 0.;
+        }
+        let (cos2_phi, sin2_phi) = Self::cos2_sin2_phi(w);
+        let alpha2 =
+            cos2_phi * self.alpha_x * self.alpha_x + sin2_phi * self.alpha_y * self.alpha_y;
+        ((1. + alpha2 * tan2).sqrt() - 1.) / 2.
+    }
+
+    // fraction of microfacets visible from `w`
+    pub fn g1(&self, w: &Vec3) -> f64 {
+        1. / (1. + self.lambda(w))
+    }
+
+    // fraction visible from both directions
+    pub fn g(&self, wo: &Vec3, wi: &Vec3) -> f64 {
+        1. / (1. + self.lambda(wo) + self.lambda(wi))
+    }
+
+    // density of the normals visible from `w`, which is what sample_wm
+    // draws from
+    pub fn visible_d(&self, w: &Vec3, wm: &Vec3) -> f64 {
+        if w.z == 0. {
+            return 0.;
+        }
+        self.g1(w) / w.z.abs() * self.d(wm) * Vec3::dot(w, wm).abs()
+    }
+
+    // Heitz's visible normal sampling: stretch `w` into the space where the
+    // distribution is a hemisphere, sample the projected disk there and map
+    // the result back
+    pub fn sample_wm(&self, w: &Vec3, u: (f64, f64)) -> Vec3 {
+        let mut wh = Vec3::unit_vector(&Vec3::new(self.alpha_x * w.x, self.alpha_y * w.y, w.z));
+        if wh.z < 0. {
+            wh = -wh;
+        }
+
+        let t1 = if wh.z < 0.99999 {
+            Vec3::unit_vector(&Vec3::cross(&Vec3::new(0., 0., 1.), &wh))
+        } else {
+            Vec3::new(1., 0., 0.)
+        };
+        let t2 = Vec3::cross(&wh, &t1);
+
+        // uniform point on the disk, squashed to the part seen from `w`
+        let r = u.0.sqrt();
+        let phi = 2. * PI * u.1;
+        let px = r * phi.cos();
+        let mut py = r * phi.sin();
+        let h = (1. - px * px).sqrt();
+        let t = (1. + wh.z) / 2.;
+        py = (1. - t) * h + t * py;
+        let pz = f64::max(0., 1. - px * px - py * py).sqrt();
+
+        let nh = t1 * px + t2 * py + wh * pz;
+        Vec3::unit_vector(&Vec3::new(
+            self.alpha_x * nh.x,
+            self.alpha_y * nh.y,
+            f64::max(1e-6, nh.z),
+        ))
+    }
+}
+
+// Exact unpolarized Fresnel reflectance of a dielectric boundary with
+// relative index of refraction `eta` (transmitted side over incident side).
+// A negative cos_theta_i means light arrives from the other side.
+pub fn fresnel_dielectric(cos_theta_i: f64, eta: f64) -> f64 {
+    let (cos_i, eta) = if cos_theta_i < 0. {
+        (f64::min(-cos_theta_i, 1.), 1. / eta)
+    } else {
+        (f64::min(cos_theta_i, 1.), eta)
+    };
+
+    let sin2_t = (1. - cos_i * cos_i) / (eta * eta);
+    if sin2_t >= 1. {
+        // total internal reflection
+        return 1.;
+    }
+    let cos_t = (1. - sin2_t).sqrt();
+
+    let r_parallel = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let r_perpendicular = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    (r_parallel * r_parallel + r_perpendicular * r_perpendicular) / 2.
+}
+
+// Direction of `wi` refracted through a boundary with normal `n`, both
+// pointing away from the surface on the same side, or None on total internal
+// reflection. `eta` is the transmitted side's index over the incident side's.
+pub fn refract(wi: &Vec3, n: &Vec3, eta: f64) -> Option<Vec3> {
+    let cos_i = Vec3::dot(n, wi);
+    let sin2_t = f64::max(0., 1. - cos_i * cos_i) / (eta * eta);
+    if sin2_t >= 1. {
+        return None;
+    }
+    let cos_t = (1. - sin2_t).sqrt();
+    Some(-*wi / eta + *n * (cos_i / eta - cos_t))
+}
+
+// The microfacet normal that scatters `wo` into `wi`, by reflection when both
+// are above the surface and by refraction into a medium of relative index
+// `eta` otherwise, together with the ratio of indices across the scattering
+// (1 for reflection). None when that microfacet would face away from either
+// direction. Expects wo.z > 0.
+pub fn generalized_half_vector(wo: &Vec3, wi: &Vec3, eta: f64) -> Option<(Vec3, f64)> {
+    let etap = if wi.z > 0. { 1. } else { eta };
+    let wm = *wi * etap + *wo;
+    if wi.z == 0. || wm.norm_squared() == 0. {
+        return None;
+    }
+    let mut wm = Vec3::unit_vector(&wm);
+    if wm.z < 0. {
+        wm = -wm;
+    }
+    if Vec3::dot(&wm, wi) * wi.z < 0. || Vec3::dot(&wm, wo) * wo.z < 0. {
+        return None;
+    }
+    Some((wm, etap))
+}
+
+// Fresnel reflectance of a conductor with complex index of refraction
+// eta + ik, for light arriving at cos_theta_i from the normal
+pub fn fresnel_complex(cos_theta_i: f64, eta: f64, k: f64) -> f64 {
+    let cos_i = cos_theta_i.clamp(0., 1.);
+    let eta = Complex::new(eta, k);
+    let one = Complex::new(1., 0.);
+    let sin2_i = Complex::new(1. - cos_i * cos_i, 0.);
+    let sin2_t = sin2_i.div(&eta.mul(&eta));
+    let cos_t = one.sub(&sin2_t).sqrt();
+    let cos_i = Complex::new(cos_i, 0.);
+
+    let r_parallel = eta
+        .mul(&cos_i)
+        .sub(&cos_t)
+        .div(&eta.mul(&cos_i).add(&cos_t));
+    let r_perpendicular = cos_i
+        .sub(&eta.mul(&cos_t))
+        .div(&cos_i.add(&eta.mul(&cos_t)));
+    (r_parallel.norm() + r_perpendicular.norm()) / 2.
+}
+
+// Reflectance of a surface under a thin transparent film, from the Airy sum
+// over all the reflections bouncing between the film's two sides. Light
+// arrives at cos_theta_i through a medium of index `eta_outside` onto a film
+// of index `eta_film` and `thickness` nanometres, over a base of complex
+// index `eta_base` + i `k_base` (k is zero for dielectrics). The waves
+// reflected off the film's top and bottom interfere, so the result swings
+// with wavelength and angle.
+pub fn thin_film_reflectance(
+    cos_theta_i: f64,
+    eta_outside: f64,
+    eta_film: f64,
+    thickness: f64,
+    eta_base: f64,
+    k_base: f64,
+    wavelength: f64,
+) -> f64 {
+    let cos_0 = cos_theta_i.clamp(0., 1.);
+    let one = Complex::new(1., 0.);
+    let n0 = Complex::new(eta_outside, 0.);
+    let n1 = Complex::new(eta_film, 0.);
+    let n2 = Complex::new(eta_base, k_base);
+
+    // Snell's law for the cosines inside each layer; complex past the
+    // critical angle and in the absorbing base
+    let sin2_n2 = Complex::new((1. - cos_0 * cos_0) * eta_outside * eta_outside, 0.);
+    let cos_in = |n: &Complex| one.sub(&sin2_n2.div(&n.mul(n))).sqrt();
+    let c0 = Complex::new(cos_0, 0.);
+    let c1 = cos_in(&n1);
+    let c2 = cos_in(&n2);
+
+    // amplitude reflection coefficients of one interface, per polarization
+    let r_s = |ni: &Complex, ci: &Complex, nj: &Complex, cj: &Complex| {
+        let a = ni.mul(ci);
+        let b = nj.mul(cj);
+        a.sub(&b).div(&a.add(&b))
+    };
+    let r_p = |ni: &Complex, ci: &Complex, nj: &Complex, cj: &Complex| {
+        let a = nj.mul(ci);
+        let b = ni.mul(cj);
+        a.sub(&b).div(&a.add(&b))
+    };
+
+    // phase picked up by one round trip through the film
+    let phase = n1
+        .mul(&c1)
+        .mul(&Complex::new(4. * PI * thickness / wavelength, 0.))
+        .exp_i();
+    let airy = |r01: Complex, r12: Complex| {
+        let delayed = r12.mul(&phase);
+        let numerator = r01.add(&delayed);
+        let denominator = one.add(&r01.mul(&delayed));
+        numerator.norm() / denominator.norm()
+    };
+
+    let s = airy(r_s(&n0, &c0, &n1, &c1), r_s(&n1, &c1, &n2, &c2));
+    let p = airy(r_p(&n0, &c0, &n1, &c1), r_p(&n1, &c1, &n2, &c2));
+    ((s + p) / 2.).clamp(0., 1.)
+}
+
+#[derive(Copy, Clone)]
+struct Complex {
+    re: f64,
+    im: f64,
+}
+
+impl Complex {
+    fn new(re: f64, im: f64) -> Complex {
+        Complex { re, im }
+    }
+
+    fn add(&self, other: &Complex) -> Complex {
+        Complex::new(self.re + other.re, self.im + other.im)
+    }
+
+    fn sub(&self, other: &Complex) -> Complex {
+        Complex::new(self.re - other.re, self.im - other.im)
+    }
+
+    fn mul(&self, other: &Complex) -> Complex {
+        Complex::new(
+            self.re * other.re - self.im * other.im,
+            self.re * other.im + self.im * other.re,
+        )
+    }
+
+    fn div(&self, other: &Complex) -> Complex {
+        let scale = 1. / (other.re * other.re + other.im * other.im);
+        Complex::new(
+            scale * (self.re * other.re + self.im * other.im),
+            scale * (self.im * other.re - self.re * other.im),
+        )
+    }
+
+    // squared magnitude
+    fn norm(&self) -> f64 {
+        self.re * self.re + self.im * self.im
+    }
+
+    // e^(i self)
+    fn exp_i(&self) -> Complex {
+        let magnitude = (-self.im).exp();
+        Complex::new(magnitude * self.re.cos(), magnitude * self.re.sin())
+    }
+
+    // principal square root
+    fn sqrt(&self) -> Complex {
+        let n = self.norm().sqrt();
+        if n == 0. {
+            return Complex::new(0., 0.);
+        }
+        let t1 = (0.5 * (n + self.re.abs())).sqrt();
+        let t2 = 0.5 * self.im / t1;
+        if self.re >= 0. {
+            Complex::new(t1, t2)
+        } else {
+            Complex::new(t2.abs(), t1.copysign(self.im))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::microfacet::{fresnel_dielectric, thin_film_reflectance};
+
+    #[test]
+    fn test_fresnel_dielectric() {
+        // ((n - 1) / (n + 1))^2 head on, and total internal reflection from
+        // inside past the critical angle
+        assert!((fresnel_dielectric(1., 1.5) - 0.04).abs() < 1e-12);
+        assert_eq!(fresnel_dielectric(-0.5, 1.5), 1.);
+        assert_eq!(fresnel_dielectric(0., 1.5), 1.);
+    }
+
+    #[test]
+    fn test_thin_film_vanishes() {
+        // a film of no thickness leaves plain Fresnel reflection, whatever its
+        // index
+        for cos_theta in [1., 0.7, 0.2] {
+            let film = thin_film_reflectance(cos_theta, 1., 1.33, 0., 1.5, 0., 550.);
+            assert!((film - fresnel_dielectric(cos_theta, 1.5)).abs() < 1e-9);
+        }
+    }
+}
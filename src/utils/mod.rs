@@ -1,9 +1,26 @@
+pub mod aabb;
+pub mod aov;
 pub mod camera;
 pub mod color;
+pub mod debug_integrator;
+pub mod denoiser;
+pub mod filter;
+pub mod heightfield;
 pub mod hittable;
+pub mod integrator;
 pub mod interval;
 pub mod material;
+pub mod microfacet;
+pub mod onb;
+pub mod options;
+pub mod pdf;
 pub mod point3;
 pub mod ray;
+pub mod sampler;
+pub mod sdf;
+pub mod spectrum;
 pub mod sphere;
+pub mod texture;
 pub mod vec3;
+pub mod volume;
+pub mod voxel_grid;
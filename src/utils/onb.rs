@@ -0,0 +1,54 @@
+use super::vec3::Vec3;
+
+// orthonormal basis with `w` along the given direction, for building local
+// frames around surface normals
+#[derive(Copy, Clone)]
+pub struct Onb {
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+}
+
+impl Onb {
+    pub fn new(n: &Vec3) -> Onb {
+        let w = Vec3::unit_vector(n);
+        let a = if w.x.abs() > 0.9 {
+            Vec3::new(0., 1., 0.)
+        } else {
+            Vec3::new(1., 0., 0.)
+        };
+        let v = Vec3::unit_vector(&Vec3::cross(&w, &a));
+        let u = Vec3::cross(&w, &v);
+
+        Onb { u, v, w }
+    }
+
+    // like new, but with `u` along `tangent` made perpendicular to `n`, so
+    // the frame follows the surface instead of the world axes; falls back to
+    // new when the tangent is zero or parallel to `n`
+    pub fn from_tangent(n: &Vec3, tangent: &Vec3) -> Onb {
+        let w = Vec3::unit_vector(n);
+        let u = *tangent - w * Vec3::dot(tangent, &w);
+        if u.norm_squared() <= 1e-12 * tangent.norm_squared() {
+            return Onb::new(n);
+        }
+        let u = Vec3::unit_vector(&u);
+        let v = Vec3::cross(&w, &u);
+
+        Onb { u, v, w }
+    }
+
+    // local coordinates -> world
+    pub fn transform(&self, v: &Vec3) -> Vec3 {
+        self.u * v.x + self.v * v.y + self.w * v.z
+    }
+
+    // world -> local coordinates
+    pub fn to_local(&self, v: &Vec3) -> Vec3 {
+        Vec3::new(
+            Vec3::dot(v, &self.u),
+            Vec3::dot(v, &self.v),
+            Vec3::dot(v, &self.w),
+        )
+    }
+}
@@ -0,0 +1,148 @@
+use super::aov::Aov;
+use super::camera::Camera;
+use super::color::ToneMapping;
+use super::debug_integrator::{DebugIntegrator, DebugMode};
+use super::denoiser::Denoiser;
+use super::filter::{Filter, FilterKind};
+use super::integrator::{AmbientOcclusion, DirectLighting, IterativePathTracer, PathTracer};
+use super::sampler::SamplerKind;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum IntegratorKind {
+    Path,
+    RecursivePath,
+    Direct,
+    AmbientOcclusion,
+}
+
+impl IntegratorKind {
+    pub const NAMES: [&'static str; 4] = ["path", "recursive-path", "direct", "ao"];
+
+    pub fn from_name(name: &str) -> Option<IntegratorKind> {
+        match name {
+            "path" => Some(IntegratorKind::Path),
+            "recursive-path" => Some(IntegratorKind::RecursivePath),
+            "direct" => Some(IntegratorKind::Direct),
+            "ao" => Some(IntegratorKind::AmbientOcclusion),
+            _ => None,
+        }
+    }
+}
+
+// Render settings picked on the command line, applied on top of whatever
+// camera an example sets up.
+pub struct RenderOptions {
+    // None keeps the example's own integrator
+    pub integrator: Option<IntegratorKind>,
+    pub ao_samples: u32,
+    pub ao_distance: f64,
+    // only the path integrator can render spectrally
+    pub spectral: bool,
+    // overrides the integrator when set
+    pub debug_mode: Option<DebugMode>,
+    pub seed: u64,
+    pub sampler: SamplerKind,
+    // adaptive sampling is on when a noise threshold is given
+    pub noise_threshold: Option<f64>,
+    pub min_samples: u32,
+    // None keeps the example's samples per pixel
+    pub max_samples: Option<u32>,
+    pub spp_map_path: Option<String>,
+    pub filter: FilterKind,
+    // None uses the filter's usual radius
+    pub filter_radius: Option<f64>,
+    pub exposure: f64,
+    pub tone_mapping: ToneMapping,
+    pub white_point: f64,
+    pub dither: bool,
+    pub aovs: Vec<Aov>,
+    pub aov_prefix: String,
+    // None leaves the image noisy
+    pub denoiser: Option<Denoiser>,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            integrator: None,
+            ao_samples: 16,
+            ao_distance: 1.,
+            spectral: false,
+            debug_mode: None,
+            seed: 0,
+            sampler: SamplerKind::Independent,
+            noise_threshold: None,
+            min_samples: 16,
+            max_samples: None,
+            spp_map_path: None,
+            filter: FilterKind::Box,
+            filter_radius: None,
+            exposure: 0.,
+            tone_mapping: ToneMapping::None,
+            white_point: 4.,
+            dither: false,
+            aovs: Vec::new(),
+            aov_prefix: String::from("aov"),
+            denoiser: None,
+        }
+    }
+}
+
+impl RenderOptions {
+    pub fn configure(&self, camera: &mut Camera) {
+        camera.seed = self.seed;
+        camera.sampler = self.sampler;
+        camera.noise_threshold = self.noise_threshold;
+        camera.min_samples_per_pixel = self.min_samples;
+        if let Some(max_samples) = self.max_samples {
+            camera.samples_per_pixel = max_samples;
+        }
+        camera.spp_map_path = self.spp_map_path.clone();
+        camera.filter = Filter::new(
+            self.filter,
+            self.filter_radius.unwrap_or(self.filter.default_radius()),
+        );
+        camera.color_pipeline.exposure = self.exposure;
+        camera.color_pipeline.tone_mapping = self.tone_mapping;
+        camera.color_pipeline.white_point = self.white_point;
+        camera.color_pipeline.dither = self.dither;
+        camera.aovs = self.aovs.clone();
+        camera.aov_prefix = self.aov_prefix.clone();
+        camera.denoiser = self.denoiser.clone();
+
+        match self.integrator {
+            Some(IntegratorKind::Path) => {
+                camera.integrator = Box::new(IterativePathTracer::default());
+            }
+            Some(IntegratorKind::RecursivePath) => {
+                camera.integrator = Box::new(PathTracer);
+            }
+            Some(IntegratorKind::Direct) => {
+                camera.integrator = Box::new(DirectLighting);
+            }
+            Some(IntegratorKind::AmbientOcclusion) => {
+                camera.integrator = Box::new(AmbientOcclusion {
+                    samples: self.ao_samples,
+                    max_distance: self.ao_distance,
+                });
+            }
+            None => {}
+        }
+
+        if self.spectral {
+            match self.integrator {
+                None | Some(IntegratorKind::Path) => {
+                    camera.integrator = Box::new(IterativePathTracer {
+                        spectral: true,
+                        ..IterativePathTracer::default()
+                    });
+                }
+                Some(_) => eprintln!("Spectral rendering needs the path integrator; using RGB"),
+            }
+        }
+
+        if let Some(mode) = self.debug_mode {
+            camera.integrator = Box::new(DebugIntegrator::new(mode));
+        }
+    }
+}
@@ -0,0 +1,393 @@
+use super::microfacet::{fresnel_dielectric, generalized_half_vector, refract, TrowbridgeReitz};
+use super::onb::Onb;
+use super::sampler::Sampler;
+use super::vec3::Vec3;
+
+// A distribution over directions: `generate` draws one, `value` gives its
+// probability density with respect to solid angle.
+pub trait Pdf {
+    fn value(&self, direction: &Vec3) -> f64;
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3;
+}
+
+pub struct SpherePdf;
+
+impl Pdf for SpherePdf {
+    fn value(&self, _direction: &Vec3) -> f64 {
+        1. / (4. * std::f64::consts::PI)
+    }
+
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        Vec3::random_unit_vector(sampler)
+    }
+}
+
+pub struct CosinePdf {
+    uvw: Onb,
+}
+
+impl CosinePdf {
+    pub fn new(w: &Vec3) -> CosinePdf {
+        CosinePdf { uvw: Onb::new(w) }
+    }
+}
+
+impl Pdf for CosinePdf {
+    fn value(&self, direction: &Vec3) -> f64 {
+        let cosine_theta = Vec3::dot(&Vec3::unit_vector(direction), &self.uvw.w);
+        f64::max(0., cosine_theta / std::f64::consts::PI)
+    }
+
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        self.uvw.transform(&Vec3::random_cosine_direction(sampler))
+    }
+}
+
+// Henyey-Greenstein phase function around the direction light was travelling
+// in; `g` > 0 scatters mostly forwards, < 0 mostly backwards and 0 evenly.
+// Sampled exactly, so it is also the phase function's value.
+pub struct HenyeyGreensteinPdf {
+    pub frame: Onb,
+    pub g: f64,
+}
+
+impl HenyeyGreensteinPdf {
+    pub fn new(travel_direction: &Vec3, g: f64) -> HenyeyGreensteinPdf {
+        HenyeyGreensteinPdf {
+            frame: Onb::new(travel_direction),
+            g: g.clamp(-0.99, 0.99),
+        }
+    }
+}
+
+impl Pdf for HenyeyGreensteinPdf {
+    fn value(&self, direction: &Vec3) -> f64 {
+        let cos_theta = Vec3::dot(&Vec3::unit_vector(direction), &self.frame.w);
+        let denominator = 1. + self.g * self.g - 2. * self.g * cos_theta;
+        (1. - self.g * self.g) / (4. * std::f64::consts::PI * denominator * denominator.sqrt())
+    }
+
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        let (u1, u2) = sampler.get_2d();
+        let g = self.g;
+        let cos_theta = if g.abs() < 1e-3 {
+            1. - 2. * u1
+        } else {
+            let s = (1. - g * g) / (1. - g + 2. * g * u1);
+            ((1. + g * g - s * s) / (2. * g)).clamp(-1., 1.)
+        };
+        let sin_theta = f64::max(0., 1. - cos_theta * cos_theta).sqrt();
+        let phi = 2. * std::f64::consts::PI * u2;
+        self.frame.transform(&Vec3::new(
+            sin_theta * phi.cos(),
+            sin_theta * phi.sin(),
+            cos_theta,
+        ))
+    }
+}
+
+// Visible-normal sampling of a microfacet reflection lobe. `wo` is the
+// direction back towards the viewer, in `frame`'s local coordinates.
+pub struct MicrofacetReflectionPdf {
+    pub frame: Onb,
+    pub wo: Vec3,
+    pub distribution: TrowbridgeReitz,
+}
+
+impl Pdf for MicrofacetReflectionPdf {
+    fn value(&self, direction: &Vec3) -> f64 {
+        let wi = self.frame.to_local(&Vec3::unit_vector(direction));
+        if self.wo.z <= 0. || wi.z <= 0. {
+            return 0.;
+        }
+        let wm = Vec3::unit_vector(&(wi + self.wo));
+        // reflecting about wm turns the density of wm into that of wi
+        self.distribution.visible_d(&self.wo, &wm) / (4. * Vec3::dot(&self.wo, &wm).abs())
+    }
+
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        let wm = self.distribution.sample_wm(&self.wo, sampler.get_2d());
+        self.frame.transform(&Vec3::reflect(&-self.wo, &wm))
+    }
+}
+
+// Visible-normal sampling for a rough dielectric: after picking a microfacet
+// normal, reflect with the Fresnel reflectance as probability and refract
+// otherwise. `eta` is the index on the far side of the surface over the
+// index on the side `wo` is on. A microfacet can send light to the wrong side
+// of the surface itself; such samples come out as the zero vector, which has
+// no density, since value() and eval() would count them as the other lobe.
+pub struct MicrofacetDielectricPdf {
+    pub frame: Onb,
+    pub wo: Vec3,
+    pub distribution: TrowbridgeReitz,
+    pub eta: f64,
+}
+
+impl Pdf for MicrofacetDielectricPdf {
+    fn value(&self, direction: &Vec3) -> f64 {
+        if direction.near_zero() {
+            return 0.;
+        }
+        let wi = self.frame.to_local(&Vec3::unit_vector(direction));
+        let (wm, etap) = match generalized_half_vector(&self.wo, &wi, self.eta) {
+            Some(half_vector) if self.wo.z > 0. => half_vector,
+            _ => return 0.,
+        };
+
+        let wo_dot_wm = Vec3::dot(&self.wo, &wm);
+        let wi_dot_wm = Vec3::dot(&wi, &wm);
+        let reflectance = fresnel_dielectric(wo_dot_wm, self.eta);
+        let visible_d = self.distribution.visible_d(&self.wo, &wm);
+        if wi.z > 0. {
+            visible_d / (4. * wo_dot_wm.abs()) * reflectance
+        } else {
+            let denominator = wi_dot_wm + wo_dot_wm / etap;
+            let dwm_dwi = wi_dot_wm.abs() / (denominator * denominator);
+            visible_d * dwm_dwi * (1. - reflectance)
+        }
+    }
+
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        let choice = sampler.get_1d();
+        let wm = self.distribution.sample_wm(&self.wo, sampler.get_2d());
+        let wi = if choice < fresnel_dielectric(Vec3::dot(&self.wo, &wm), self.eta) {
+            Some(Vec3::reflect(&-self.wo, &wm)).filter(|reflected| reflected.z > 0.)
+        } else {
+            // refraction only fails under total internal reflection, where
+            // the reflectance is 1 and the branch above is always taken
+            refract(&self.wo, &wm, self.eta).filter(|refracted| refracted.z < 0.)
+        };
+        match wi {
+            Some(wi) => self.frame.transform(&wi),
+            None => Vec3::zero(),
+        }
+    }
+}
+
+// One-sample mixture of any number of strategies, each picked with
+// probability proportional to its weight; used by materials made of several
+// lobes. Zero-weight entries are never picked.
+#[derive(Default)]
+pub struct LobeMixturePdf {
+    lobes: Vec<(f64, Box<dyn Pdf>)>,
+    total_weight: f64,
+}
+
+impl LobeMixturePdf {
+    pub fn add(&mut self, weight: f64, pdf: Box<dyn Pdf>) {
+        if weight > 0. {
+            self.total_weight += weight;
+            self.lobes.push((weight, pdf));
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lobes.is_empty()
+    }
+}
+
+impl Pdf for LobeMixturePdf {
+    fn value(&self, direction: &Vec3) -> f64 {
+        // a lobe's rejected sample, see MicrofacetDielectricPdf
+        if direction.near_zero() {
+            return 0.;
+        }
+        self.lobes
+            .iter()
+            .map(|(weight, pdf)| weight * pdf.value(direction))
+            .sum::<f64>()
+            / self.total_weight
+    }
+
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        let mut choice = sampler.get_1d() * self.total_weight;
+        for (weight, pdf) in &self.lobes[..self.lobes.len() - 1] {
+            if choice < *weight {
+                return pdf.generate(sampler);
+            }
+            choice -= weight;
+        }
+        self.lobes.last().unwrap().1.generate(sampler)
+    }
+}
+
+// Directions drawn from `base` underneath a smooth coat with relative index
+// `eta`, bent where they leave the coat. `weight` is the chance this is
+// sampled at all, the rest going to the coat's mirror reflection. Directions
+// into the surface pass straight through the thin coat; ones that would be
+// totally internally reflected come out as the zero vector, which has no
+// density.
+pub struct CoatedPdf {
+    pub normal: Vec3,
+    pub eta: f64,
+    pub weight: f64,
+    pub base: Box<dyn Pdf>,
+}
+
+impl Pdf for CoatedPdf {
+    fn value(&self, direction: &Vec3) -> f64 {
+        if direction.near_zero() {
+            return 0.;
+        }
+        let direction = Vec3::unit_vector(direction);
+        let cos_outside = Vec3::dot(&self.normal, &direction);
+        if cos_outside <= 0. {
+            return self.weight * self.base.value(&direction);
+        }
+        let inside = match refract(&direction, &self.normal, self.eta) {
+            Some(refracted) => -refracted,
+            None => return 0.,
+        };
+
+        // refraction squeezes solid angle by cos_outside / (eta^2 cos_inside)
+        let cos_inside = Vec3::dot(&self.normal, &inside);
+        self.weight * self.base.value(&inside) * cos_outside / (self.eta * self.eta * cos_inside)
+    }
+
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        let inside = Vec3::unit_vector(&self.base.generate(sampler));
+        if Vec3::dot(&self.normal, &inside) <= 0. {
+            return inside;
+        }
+        match refract(&inside, &self.normal, 1. / self.eta) {
+            Some(refracted) => -refracted,
+            None => Vec3::zero(),
+        }
+    }
+}
+
+// multiple importance sampling weight (power heuristic, beta = 2) for a sample
+// drawn from the strategy with density `pdf_f` when `pdf_g` could also produce it
+pub fn power_heuristic(pdf_f: f64, pdf_g: f64) -> f64 {
+    let f = pdf_f * pdf_f;
+    let g = pdf_g * pdf_g;
+    if f + g == 0. {
+        return 0.;
+    }
+    f / (f + g)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::{
+        hittable::HitRecord,
+        material::{Material, RoughDielectric, ScatterRecord},
+        pdf::{CoatedPdf, CosinePdf, HenyeyGreensteinPdf, Pdf},
+        ray::Ray,
+        sampler::IndependentSampler,
+        vec3::Vec3,
+    };
+
+    // midpoint rule over a grid uniform in cos(theta) and phi
+    fn integrate_over_sphere(f: impl Fn(&Vec3) -> f64) -> f64 {
+        let n = 400;
+        let mut sum = 0.;
+        for i in 0..n {
+            let cos_theta = 1. - 2. * (i as f64 + 0.5) / n as f64;
+            let sin_theta = (1. - cos_theta * cos_theta).sqrt();
+            for j in 0..n {
+                let phi = 2. * std::f64::consts::PI * (j as f64 + 0.5) / n as f64;
+                sum += f(&Vec3::new(
+                    sin_theta * phi.cos(),
+                    sin_theta * phi.sin(),
+                    cos_theta,
+                ));
+            }
+        }
+        sum * 4. * std::f64::consts::PI / (n * n) as f64
+    }
+
+    #[test]
+    fn test_coated_pdf_integrates_to_at_most_one() {
+        // only the part of the base lobe inside the critical angle can be
+        // reached from above the coat: 1 / eta^2 of a cosine lobe
+        let normal = Vec3::new(0., 0., 1.);
+        let eta = 1.5;
+        let pdf = CoatedPdf {
+            normal,
+            eta,
+            weight: 1.,
+            base: Box::new(CosinePdf::new(&normal)),
+        };
+        let integral = integrate_over_sphere(|direction| pdf.value(direction));
+        assert!(integral <= 1.);
+        assert!((integral - 1. / (eta * eta)).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_henyey_greenstein_matches_its_samples() {
+        let travel_direction = Vec3::unit_vector(&Vec3::new(1., 2., -0.5));
+        for g in [-0.7, 0.7] {
+            let pdf = HenyeyGreensteinPdf::new(&travel_direction, g);
+            let integral = integrate_over_sphere(|direction| pdf.value(direction));
+            assert!((integral - 1.).abs() < 1e-3);
+
+            // histogram of the cosine to the travel direction against the
+            // probability value() gives each bin
+            let bins = 20;
+            let samples = 200000;
+            let mut histogram = vec![0; bins];
+            let mut sampler = IndependentSampler::new(11);
+            for _ in 0..samples {
+                let direction = Vec3::unit_vector(&pdf.generate(&mut sampler));
+                let cos_theta = Vec3::dot(&direction, &travel_direction);
+                let bin = ((cos_theta + 1.) / 2. * bins as f64) as usize;
+                histogram[bin.min(bins - 1)] += 1;
+            }
+            for (bin, &count) in histogram.iter().enumerate() {
+                // the pdf integrated over the bin's band of the sphere, whose
+                // solid angle is 2 pi times its width in cos(theta)
+                let steps = 100;
+                let step = 2. / (bins * steps) as f64;
+                let expected: f64 = (0..steps)
+                    .map(|k| {
+                        let cos_theta = -1. + (bin * steps + k) as f64 * step + 0.5 * step;
+                        let sin_theta = (1. - cos_theta * cos_theta).sqrt();
+                        let direction = pdf.frame.transform(&Vec3::new(sin_theta, 0., cos_theta));
+                        pdf.value(&direction) * 2. * std::f64::consts::PI * step
+                    })
+                    .sum();
+                let observed = count as f64 / samples as f64;
+                assert!((observed - expected).abs() < 0.003 + 0.03 * expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_rough_dielectric_from_inside_matches_its_samples() {
+        // leaving glass at a grazing angle, where many microfacet samples
+        // land on the wrong side of the surface
+        let glass = RoughDielectric::new(1.5, 0.4);
+        let mut hit_record = HitRecord::default();
+        hit_record.normal = Vec3::new(0., 0., 1.);
+        hit_record.geometric_normal = hit_record.normal;
+        hit_record.front_face = false;
+        let wo = Vec3::new(f64::sqrt(1. - 0.3 * 0.3), 0., 0.3);
+        let ray_in = Ray::new(wo, -wo);
+        let eval = |direction: &Vec3| {
+            glass
+                .eval(&ray_in, &hit_record, &Ray::new(Vec3::zero(), *direction))
+                .x
+        };
+
+        let integral = integrate_over_sphere(eval);
+
+        let mut scatter_record = ScatterRecord::default();
+        let mut sampler = IndependentSampler::new(3);
+        assert!(glass.scatter(&ray_in, &hit_record, &mut scatter_record, &mut sampler));
+        let pdf = scatter_record.pdf.unwrap();
+        let samples = 200000;
+        let mut total = 0.;
+        for _ in 0..samples {
+            let direction = pdf.generate(&mut sampler);
+            let pdf_value = pdf.value(&direction);
+            if pdf_value > 0. {
+                total += eval(&direction) / pdf_value;
+            }
+        }
+        let estimate = total / samples as f64;
+
+        assert!((estimate - integral).abs() < 0.005);
+    }
+}
@@ -1,17 +1,30 @@
 use core::f64;
 
 use super::point3::Point3;
+use super::spectrum::SampledWavelengths;
 use super::vec3::Vec3;
 
-#[derive(Default)]
+#[derive(Copy, Clone, Default)]
 pub struct Ray {
     pub origin: Point3,
     pub direction: Vec3,
+    // the path's wavelengths when rendering spectrally, for materials whose
+    // behaviour depends on them
+    pub wavelengths: Option<SampledWavelengths>,
+    // seeds the random choices made along the ray by code that has no
+    // sampler, such as free flights through volumes and Mix's pick; the
+    // integrators draw it from the path's sampler for each ray they trace
+    pub seed: u64,
 }
 
 impl Ray {
     pub fn new(origin: Point3, direction: Vec3) -> Ray {
-        Ray { origin, direction }
+        Ray {
+            origin,
+            direction,
+            wavelengths: None,
+            seed: 0,
+        }
     }
 
     pub fn at(&self, t: f64) -> Point3 {
@@ -0,0 +1,640 @@
+use std::sync::OnceLock;
+
+// Source of every random number used while rendering. Camera::render
+// restarts it for each pixel sample, after which each request takes the next
+// dimension(s): the camera always uses 0-1 for the position within the pixel,
+// 2 for time and 3-4 for the lens, then each bounce continues where the last
+// one stopped. Keeping that order fixed is what lets the low-discrepancy
+// samplers line up matching dimensions across the samples of a pixel. All of
+// them depend only on the seed, the pixel and the sample index, so an image
+// comes out bit-identical from run to run.
+pub trait Sampler {
+    fn start_pixel_sample(&mut self, i: u32, j: u32, sample_index: u32);
+
+    // uniform in [0, 1)
+    fn get_1d(&mut self) -> f64;
+
+    fn get_2d(&mut self) -> (f64, f64);
+
+    // key for a stream of random choices made where no sampler can be passed
+    // along, see Ray::seed
+    fn get_seed(&mut self) -> u64 {
+        (self.get_1d() * (1u64 << 53) as f64) as u64
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum SamplerKind {
+    Independent,
+    Stratified,
+    Halton,
+    Sobol,
+    BlueNoise,
+}
+
+impl SamplerKind {
+    pub const NAMES: [&'static str; 5] =
+        ["independent", "stratified", "halton", "sobol", "blue-noise"];
+
+    pub fn from_name(name: &str) -> Option<SamplerKind> {
+        match name {
+            "independent" => Some(SamplerKind::Independent),
+            "stratified" => Some(SamplerKind::Stratified),
+            "halton" => Some(SamplerKind::Halton),
+            "sobol" => Some(SamplerKind::Sobol),
+            "blue-noise" => Some(SamplerKind::BlueNoise),
+            _ => None,
+        }
+    }
+
+    pub fn create(&self, seed: u64, samples_per_pixel: u32) -> Box<dyn Sampler> {
+        match self {
+            SamplerKind::Independent => Box::new(IndependentSampler::new(seed)),
+            SamplerKind::Stratified => Box::new(StratifiedSampler::new(seed, samples_per_pixel)),
+            SamplerKind::Halton => Box::new(HaltonSampler::new(seed)),
+            SamplerKind::Sobol => Box::new(SobolSampler::new(seed, samples_per_pixel)),
+            SamplerKind::BlueNoise => Box::new(BlueNoiseSampler::new(seed)),
+        }
+    }
+}
+
+// Uniform random numbers with no structure between samples.
+pub struct IndependentSampler {
+    seed: u64,
+    rng: Pcg32,
+}
+
+impl IndependentSampler {
+    pub fn new(seed: u64) -> IndependentSampler {
+        IndependentSampler {
+            seed,
+            rng: Pcg32::new(seed, 0),
+        }
+    }
+}
+
+impl Sampler for IndependentSampler {
+    fn start_pixel_sample(&mut self, i: u32, j: u32, sample_index: u32) {
+        self.rng = Pcg32::new(hash(&[self.seed, pixel_key(i, j)]), sample_index as u64);
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        self.rng.next_f64()
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let u = self.get_1d();
+        (u, self.get_1d())
+    }
+}
+
+// Jittered strata: within a pixel, every dimension splits [0, 1) (or the
+// unit square) into one stratum per sample, and each sample lands in a
+// different one, visited in an order shuffled per pixel and dimension.
+// 2D requests use correlated multi-jittering (Kensler): a columns x rows grid
+// with exactly one sample per cell that is also stratified along each axis,
+// which is a square grid when the sample count is a perfect square and a
+// Latin hypercube when it is prime.
+pub struct StratifiedSampler {
+    seed: u64,
+    samples_per_pixel: u32,
+    columns: u32,
+    rows: u32,
+    pixel: u64,
+    sample_index: u32,
+    dimension: u64,
+    rng: Pcg32,
+}
+
+impl StratifiedSampler {
+    pub fn new(seed: u64, samples_per_pixel: u32) -> StratifiedSampler {
+        let samples_per_pixel = u32::max(samples_per_pixel, 1);
+        // the most square grid whose cells match the samples one to one
+        let columns = (1..=(samples_per_pixel as f64).sqrt() as u32)
+            .rev()
+            .find(|&c| samples_per_pixel.is_multiple_of(c))
+            .unwrap_or(1);
+        StratifiedSampler {
+            seed,
+            samples_per_pixel,
+            columns,
+            rows: samples_per_pixel / columns,
+            pixel: 0,
+            sample_index: 0,
+            dimension: 0,
+            rng: Pcg32::new(seed, 0),
+        }
+    }
+
+    fn stratum(&mut self) -> u32 {
+        let permutation_seed = hash(&[self.seed, self.pixel, self.dimension]) as u32;
+        self.dimension += 1;
+        permutation_element(self.sample_index, self.samples_per_pixel, permutation_seed)
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    fn start_pixel_sample(&mut self, i: u32, j: u32, sample_index: u32) {
+        self.pixel = pixel_key(i, j);
+        self.sample_index = sample_index;
+        self.dimension = 0;
+        self.rng = Pcg32::new(hash(&[self.seed, self.pixel]), sample_index as u64);
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let stratum = self.stratum();
+        (stratum as f64 + self.rng.next_f64()) / self.samples_per_pixel as f64
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let (columns, rows) = (self.columns, self.rows);
+        let permutation_seed = hash(&[self.seed, self.pixel, self.dimension]) as u32;
+        let stratum = self.stratum();
+        self.dimension += 1;
+
+        // within its cell, each sample takes the sub-column and sub-row that
+        // keep the columns x rows fine strata of each axis one sample apiece
+        let (column, row) = (stratum % columns, stratum / columns);
+        let sub_column = permutation_element(row, rows, permutation_seed.wrapping_mul(0xa511e9b3));
+        let sub_row =
+            permutation_element(column, columns, permutation_seed.wrapping_mul(0x63d83595));
+        (
+            (column as f64 + (sub_column as f64 + self.rng.next_f64()) / rows as f64)
+                / columns as f64,
+            (row as f64 + (sub_row as f64 + self.rng.next_f64()) / columns as f64) / rows as f64,
+        )
+    }
+}
+
+// The Halton sequence, one prime base per dimension, with the digits
+// scrambled per pixel so neighbouring pixels don't share a pattern. Dimensions
+// past the prime table fall back to independent random numbers.
+pub struct HaltonSampler {
+    seed: u64,
+    pixel: u64,
+    sample_index: u32,
+    dimension: usize,
+    rng: Pcg32,
+}
+
+impl HaltonSampler {
+    const PRIMES: [u32; 64] = [
+        2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89,
+        97, 101, 103, 107, 109, 113, 127, 131, 137, 139, 149, 151, 157, 163, 167, 173, 179, 181,
+        191, 193, 197, 199, 211, 223, 227, 229, 233, 239, 241, 251, 257, 263, 269, 271, 277, 281,
+        283, 293, 307, 311,
+    ];
+
+    pub fn new(seed: u64) -> HaltonSampler {
+        HaltonSampler {
+            seed,
+            pixel: 0,
+            sample_index: 0,
+            dimension: 0,
+            rng: Pcg32::new(seed, 0),
+        }
+    }
+}
+
+impl Sampler for HaltonSampler {
+    fn start_pixel_sample(&mut self, i: u32, j: u32, sample_index: u32) {
+        self.pixel = pixel_key(i, j);
+        self.sample_index = sample_index;
+        self.dimension = 0;
+        self.rng = Pcg32::new(hash(&[self.seed, self.pixel]), sample_index as u64);
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let dimension = self.dimension;
+        self.dimension += 1;
+        if dimension >= Self::PRIMES.len() {
+            return self.rng.next_f64();
+        }
+
+        let scramble = hash(&[self.seed, self.pixel, dimension as u64]);
+        scrambled_radical_inverse(Self::PRIMES[dimension], self.sample_index, scramble)
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let u = self.get_1d();
+        (u, self.get_1d())
+    }
+}
+
+// Owen-scrambled Sobol points, "padded": every 1D or 2D request uses the
+// first two Sobol dimensions with its own scramble and its own shuffle of
+// the sample order, which avoids needing direction numbers for high
+// dimensions. Works best with power-of-two samples per pixel.
+pub struct SobolSampler {
+    seed: u64,
+    samples_per_pixel: u32,
+    pixel: u64,
+    sample_index: u32,
+    dimension: u64,
+}
+
+impl SobolSampler {
+    pub fn new(seed: u64, samples_per_pixel: u32) -> SobolSampler {
+        SobolSampler {
+            seed,
+            samples_per_pixel: u32::max(samples_per_pixel, 1),
+            pixel: 0,
+            sample_index: 0,
+            dimension: 0,
+        }
+    }
+
+    // shuffled sample index and a scramble seed for the next dimension
+    fn next_dimension(&mut self) -> (u32, u64) {
+        let dimension_hash = hash(&[self.seed, self.pixel, self.dimension]);
+        self.dimension += 1;
+        let index = permutation_element(
+            self.sample_index,
+            self.samples_per_pixel,
+            dimension_hash as u32,
+        );
+        (index, dimension_hash)
+    }
+}
+
+impl Sampler for SobolSampler {
+    fn start_pixel_sample(&mut self, i: u32, j: u32, sample_index: u32) {
+        self.pixel = pixel_key(i, j);
+        self.sample_index = sample_index;
+        self.dimension = 0;
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let (index, scramble) = self.next_dimension();
+        to_unit_float(owen_scramble(sobol_0(index), (scramble >> 32) as u32))
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let (index, scramble) = self.next_dimension();
+        self.dimension += 1;
+        (
+            to_unit_float(owen_scramble(sobol_0(index), (scramble >> 32) as u32)),
+            to_unit_float(owen_scramble(sobol_1(index), scramble as u32)),
+        )
+    }
+}
+
+// The same Sobol points in every pixel (scrambled per dimension, not per
+// pixel), shifted by a blue noise texture sampled at the pixel. The error of
+// neighbouring pixels is then anti-correlated, so what noise remains is fine
+// grained and much less visible than white noise at the same sample count.
+pub struct BlueNoiseSampler {
+    seed: u64,
+    i: u32,
+    j: u32,
+    sample_index: u32,
+    dimension: u64,
+}
+
+impl BlueNoiseSampler {
+    pub fn new(seed: u64) -> BlueNoiseSampler {
+        BlueNoiseSampler {
+            seed,
+            i: 0,
+            j: 0,
+            sample_index: 0,
+            dimension: 0,
+        }
+    }
+
+    fn sample(&mut self, sobol: fn(u32) -> u32) -> f64 {
+        let dimension_hash = hash(&[self.seed, self.dimension]);
+        self.dimension += 1;
+
+        // each dimension reads the texture at its own toroidal offset
+        let shift = blue_noise(
+            self.i.wrapping_add(dimension_hash as u32),
+            self.j.wrapping_add((dimension_hash >> 32) as u32),
+        );
+        let point = to_unit_float(owen_scramble(
+            sobol(self.sample_index),
+            (dimension_hash >> 16) as u32,
+        ));
+        (point + shift).fract()
+    }
+}
+
+impl Sampler for BlueNoiseSampler {
+    fn start_pixel_sample(&mut self, i: u32, j: u32, sample_index: u32) {
+        self.i = i;
+        self.j = j;
+        self.sample_index = sample_index;
+        self.dimension = 0;
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        self.sample(sobol_0)
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let u = self.sample(sobol_0);
+        (u, self.sample(sobol_1))
+    }
+}
+
+fn pixel_key(i: u32, j: u32) -> u64 {
+    ((j as u64) << 32) | i as u64
+}
+
+// 64-bit mix of a handful of values (splitmix64 finalizer per value)
+pub fn hash(values: &[u64]) -> u64 {
+    let mut h: u64 = 0x9e3779b97f4a7c15;
+    for value in values {
+        h ^= value.wrapping_add(0x9e3779b97f4a7c15).wrapping_add(h << 6) ^ (h >> 2);
+        h = (h ^ (h >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        h = (h ^ (h >> 27)).wrapping_mul(0x94d049bb133111eb);
+        h ^= h >> 31;
+    }
+    h
+}
+
+fn to_unit_float(bits: u32) -> f64 {
+    bits as f64 / (1u64 << 32) as f64
+}
+
+// element `index` of a pseudo-random permutation of 0..length chosen by
+// `seed`, without building the permutation (Kensler, "Correlated
+// Multi-Jittered Sampling")
+fn permutation_element(index: u32, length: u32, seed: u32) -> u32 {
+    let mut mask = length.wrapping_sub(1);
+    mask |= mask >> 1;
+    mask |= mask >> 2;
+    mask |= mask >> 4;
+    mask |= mask >> 8;
+    mask |= mask >> 16;
+
+    let mut i = index;
+    loop {
+        i ^= seed;
+        i = i.wrapping_mul(0xe170893d);
+        i ^= seed >> 16;
+        i ^= (i & mask) >> 4;
+        i ^= seed >> 8;
+        i = i.wrapping_mul(0x0929eb3f);
+        i ^= seed >> 23;
+        i ^= (i & mask) >> 1;
+        i = i.wrapping_mul(1 | seed >> 27);
+        i = i.wrapping_mul(0x6935fa69);
+        i ^= (i & mask) >> 11;
+        i = i.wrapping_mul(0x74dcb303);
+        i ^= (i & mask) >> 2;
+        i = i.wrapping_mul(0x9e501cc3);
+        i ^= (i & mask) >> 2;
+        i = i.wrapping_mul(0xc860a3df);
+        i &= mask;
+        i ^= i >> 5;
+        if i < length {
+            break;
+        }
+    }
+    (i.wrapping_add(seed)) % length
+}
+
+// radical inverse of `index` in `base` with each digit shifted by an amount
+// drawn from `scramble`; shifting digits keeps the sequence's stratification
+fn scrambled_radical_inverse(base: u32, index: u32, scramble: u64) -> f64 {
+    let inverse_base = 1. / base as f64;
+    let mut index = index;
+    let mut scale = inverse_base;
+    let mut result = 0.;
+    // a cheap LCG stream gives the shift for each successive digit
+    let mut shifts = scramble;
+
+    // keep going past the last nonzero digit so trailing zeros get shifted
+    // too, and fill in digits below any useful stratum size all at once
+    while index != 0 || scale > 1e-7 {
+        shifts = shifts
+            .wrapping_mul(Pcg32::MULTIPLIER)
+            .wrapping_add(1442695040888963407);
+        let shift = ((shifts >> 33) % base as u64) as u32;
+        let digit = index % base;
+        result += ((digit + shift) % base) as f64 * scale;
+
+        index /= base;
+        scale *= inverse_base;
+    }
+    result += (shifts >> 11) as f64 / (1u64 << 53) as f64 * scale * base as f64;
+    f64::min(result, 1. - f64::EPSILON)
+}
+
+// first Sobol dimension: the van der Corput sequence in base 2
+fn sobol_0(index: u32) -> u32 {
+    index.reverse_bits()
+}
+
+// second Sobol dimension
+fn sobol_1(index: u32) -> u32 {
+    let mut index = index;
+    let mut v: u32 = 1 << 31;
+    let mut result = 0;
+    while index != 0 {
+        if index & 1 != 0 {
+            result ^= v;
+        }
+        index >>= 1;
+        v ^= v >> 1;
+    }
+    result
+}
+
+// hash-based Owen scrambling (Laine-Karras style): flips each bit based on
+// the bits above it, which keeps the points' stratification intact
+fn owen_scramble(bits: u32, seed: u32) -> u32 {
+    let mut v = bits.reverse_bits();
+    v ^= v.wrapping_mul(0x3d20adea);
+    v = v.wrapping_add(seed);
+    v = v.wrapping_mul((seed >> 16) | 1);
+    v ^= v.wrapping_mul(0x05526c56);
+    v ^= v.wrapping_mul(0x53a22864);
+    v.reverse_bits()
+}
+
+const BLUE_NOISE_SIZE: usize = 64;
+
+// tiling blue noise threshold texture with values in [0, 1)
+fn blue_noise(i: u32, j: u32) -> f64 {
+    static TEXTURE: OnceLock<Vec<f64>> = OnceLock::new();
+    let texture = TEXTURE.get_or_init(|| void_and_cluster(BLUE_NOISE_SIZE));
+
+    let x = i as usize % BLUE_NOISE_SIZE;
+    let y = j as usize % BLUE_NOISE_SIZE;
+    texture[y * BLUE_NOISE_SIZE + x]
+}
+
+// Ulichney's void-and-cluster method: rank every texel by how far it is from
+// the others, measured with a toroidal Gaussian energy, so any threshold of
+// the ranks gives an evenly spread, clump-free set of points
+fn void_and_cluster(size: usize) -> Vec<f64> {
+    let n = size * size;
+    let sigma = 1.9;
+
+    let mut kernel = vec![0.; n];
+    for (k, value) in kernel.iter_mut().enumerate() {
+        let dx = usize::min(k % size, size - k % size) as f64;
+        let dy = usize::min(k / size, size - k / size) as f64;
+        *value = (-(dx * dx + dy * dy) / (2. * sigma * sigma)).exp();
+    }
+
+    let splat = |energy: &mut Vec<f64>, at: usize, sign: f64| {
+        let (ax, ay) = (at % size, at / size);
+        for (k, e) in energy.iter_mut().enumerate() {
+            let dx = (k % size + size - ax) % size;
+            let dy = (k / size + size - ay) % size;
+            *e += sign * kernel[dy * size + dx];
+        }
+    };
+    // tightest cluster: the set texel with the most energy; largest void:
+    // the empty texel with the least
+    let tightest_cluster = |energy: &[f64], pattern: &[bool]| {
+        (0..n)
+            .filter(|&k| pattern[k])
+            .max_by(|&a, &b| energy[a].total_cmp(&energy[b]))
+            .unwrap()
+    };
+    let largest_void = |energy: &[f64], pattern: &[bool]| {
+        (0..n)
+            .filter(|&k| !pattern[k])
+            .min_by(|&a, &b| energy[a].total_cmp(&energy[b]))
+            .unwrap()
+    };
+
+    // initial pattern: a random tenth of the texels, relaxed by repeatedly
+    // moving the tightest cluster into the largest void
+    let mut rng = Pcg32::new(0x5eed, 0);
+    let mut pattern = vec![false; n];
+    let mut energy = vec![0.; n];
+    let initial = n / 10;
+    let mut placed = 0;
+    while placed < initial {
+        let k = (rng.next_u32() as usize) % n;
+        if !pattern[k] {
+            pattern[k] = true;
+            splat(&mut energy, k, 1.);
+            placed += 1;
+        }
+    }
+    loop {
+        let cluster = tightest_cluster(&energy, &pattern);
+        pattern[cluster] = false;
+        splat(&mut energy, cluster, -1.);
+        let void = largest_void(&energy, &pattern);
+        if void == cluster {
+            pattern[cluster] = true;
+            splat(&mut energy, cluster, 1.);
+            break;
+        }
+        pattern[void] = true;
+        splat(&mut energy, void, 1.);
+    }
+
+    let mut rank = vec![0; n];
+
+    // phase 1: take points away from the tightest clusters, ranking downwards
+    let mut working = pattern.clone();
+    let mut working_energy = energy.clone();
+    for r in (0..initial).rev() {
+        let cluster = tightest_cluster(&working_energy, &working);
+        working[cluster] = false;
+        splat(&mut working_energy, cluster, -1.);
+        rank[cluster] = r;
+    }
+
+    // phase 2 and 3: fill the largest voids, ranking upwards
+    for r in initial..n {
+        let void = largest_void(&energy, &pattern);
+        pattern[void] = true;
+        splat(&mut energy, void, 1.);
+        rank[void] = r;
+    }
+
+    rank.iter().map(|&r| r as f64 / n as f64).collect()
+}
+
+// PCG32 (XSH RR); small, fast and with a fixed output sequence, unlike
+// generators whose streams may change between crate versions
+struct Pcg32 {
+    state: u64,
+    increment: u64,
+}
+
+impl Pcg32 {
+    const MULTIPLIER: u64 = 6364136223846793005;
+
+    fn new(seed: u64, stream: u64) -> Pcg32 {
+        let mut rng = Pcg32 {
+            state: 0,
+            increment: (stream << 1) | 1,
+        };
+        rng.next_u32();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.next_u32();
+        rng
+    }
+
+    fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old
+            .wrapping_mul(Self::MULTIPLIER)
+            .wrapping_add(self.increment);
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        let rot = (old >> 59) as u32;
+        xorshifted.rotate_right(rot)
+    }
+
+    fn next_f64(&mut self) -> f64 {
+        let bits = ((self.next_u32() as u64) << 21) ^ (self.next_u32() as u64 >> 11);
+        bits as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::sampler::{permutation_element, Sampler, StratifiedSampler};
+
+    #[test]
+    fn test_permutation_element_is_a_permutation() {
+        let mut seen = [false; 13];
+        for index in 0..13 {
+            seen[permutation_element(index, 13, 0xdeadbeef) as usize] = true;
+        }
+        assert!(seen.iter().all(|&s| s));
+    }
+
+    #[test]
+    fn test_stratified_covers_every_stratum() {
+        let mut sampler = StratifiedSampler::new(7, 8);
+        let mut strata = [0; 8];
+        for sample in 0..8 {
+            sampler.start_pixel_sample(3, 5, sample);
+            strata[(sampler.get_1d() * 8.) as usize] += 1;
+        }
+        assert_eq!(strata, [1; 8]);
+    }
+
+    #[test]
+    fn test_stratified_2d_covers_unit_square() {
+        // 5 samples don't fill a square grid; every fifth of each axis still
+        // gets exactly one, and no part of the square is left out
+        let mut quadrants = [0; 4];
+        for pixel in 0..16 {
+            let mut sampler = StratifiedSampler::new(7, 5);
+            let mut columns = [0; 5];
+            let mut rows = [0; 5];
+            for sample in 0..5 {
+                sampler.start_pixel_sample(pixel, 2, sample);
+                let (x, y) = sampler.get_2d();
+                columns[(x * 5.) as usize] += 1;
+                rows[(y * 5.) as usize] += 1;
+                quadrants[(x >= 0.5) as usize + 2 * (y >= 0.5) as usize] += 1;
+            }
+            assert_eq!(columns, [1; 5]);
+            assert_eq!(rows, [1; 5]);
+        }
+        assert!(quadrants.iter().all(|&q| q > 0));
+    }
+}
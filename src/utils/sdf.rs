@@ -0,0 +1,303 @@
+use super::aabb::Aabb;
+use super::hittable::{HitRecord, Hittable};
+use super::interval::Interval;
+use super::material::Material;
+use super::point3::Point3;
+use super::ray::Ray;
+use super::sphere::Sphere;
+use super::vec3::Vec3;
+
+// A shape given implicitly by its signed distance: negative inside, positive
+// outside, and never larger than the true distance to the surface.
+pub trait Sdf {
+    fn distance(&self, p: &Point3) -> f64;
+
+    // box the surface is guaranteed to lie in, used to clip the march
+    fn bounding_box(&self) -> Aabb;
+}
+
+pub struct SdfSphere {
+    pub center: Point3,
+    pub radius: f64,
+}
+
+impl Sdf for SdfSphere {
+    fn distance(&self, p: &Point3) -> f64 {
+        (*p - self.center).norm() - self.radius
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let r = Vec3::new(self.radius, self.radius, self.radius);
+        Aabb::new(self.center - r, self.center + r)
+    }
+}
+
+pub struct RoundedBox {
+    pub center: Point3,
+    pub half_extents: Vec3,
+    pub radius: f64,
+}
+
+impl Sdf for RoundedBox {
+    fn distance(&self, p: &Point3) -> f64 {
+        let local = *p - self.center;
+        let q = Vec3::new(
+            local.x.abs() - self.half_extents.x + self.radius,
+            local.y.abs() - self.half_extents.y + self.radius,
+            local.z.abs() - self.half_extents.z + self.radius,
+        );
+        let outside = Vec3::new(q.x.max(0.), q.y.max(0.), q.z.max(0.)).norm();
+        let inside = f64::min(f64::max(q.x, f64::max(q.y, q.z)), 0.);
+
+        outside + inside - self.radius
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        Aabb::new(
+            self.center - self.half_extents,
+            self.center + self.half_extents,
+        )
+    }
+}
+
+// Prism with an equilateral triangle for a cross section, apex up, running
+// along the x axis. `size` is the height of the triangle's inscribed circle
+// times three over two, as in Inigo Quilez's formula.
+pub struct TriangularPrism {
+    pub center: Point3,
+    pub size: f64,
+    pub half_length: f64,
+}
+
+impl Sdf for TriangularPrism {
+    fn distance(&self, p: &Point3) -> f64 {
+        let local = *p - self.center;
+        let across = f64::max(local.z.abs() * 0.866025 + local.y * 0.5, -local.y) - self.size * 0.5;
+        f64::max(local.x.abs() - self.half_length, across)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        Aabb::new(
+            self.center - Vec3::new(self.half_length, self.size * 0.5, self.size),
+            self.center + Vec3::new(self.half_length, self.size, self.size),
+        )
+    }
+}
+
+// polynomial smooth minimum of two shapes; `k` is the blend radius
+pub struct SmoothUnion<'a> {
+    pub a: &'a dyn Sdf,
+    pub b: &'a dyn Sdf,
+    pub k: f64,
+}
+
+impl Sdf for SmoothUnion<'_> {
+    fn distance(&self, p: &Point3) -> f64 {
+        let d1 = self.a.distance(p);
+        let d2 = self.b.distance(p);
+        if self.k <= 0. {
+            return f64::min(d1, d2);
+        }
+
+        let h = (0.5 + 0.5 * (d2 - d1) / self.k).clamp(0., 1.);
+        d2 * (1. - h) + d1 * h - self.k * h * (1. - h)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        // the blend can bulge out by at most k / 4
+        let bulge = Vec3::new(self.k, self.k, self.k) * 0.25;
+        let bbox = Aabb::surrounding(&self.a.bounding_box(), &self.b.bounding_box());
+        Aabb::new(bbox.min - bulge, bbox.max + bulge)
+    }
+}
+
+pub struct Mandelbulb {
+    pub center: Point3,
+    pub scale: f64,
+    pub power: f64,
+    pub iterations: u32,
+}
+
+impl Mandelbulb {
+    pub fn new(center: Point3, scale: f64) -> Mandelbulb {
+        Mandelbulb {
+            center,
+            scale,
+            power: 8.,
+            iterations: 12,
+        }
+    }
+}
+
+impl Sdf for Mandelbulb {
+    // distance estimate from the running derivative of the iteration
+    fn distance(&self, p: &Point3) -> f64 {
+        let c = (*p - self.center) / self.scale;
+        let mut z = c;
+        let mut dr = 1.;
+        let mut r = z.norm();
+
+        for _ in 0..self.iterations {
+            if r > 2. {
+                break;
+            }
+
+            let theta = (z.z / r).acos() * self.power;
+            let phi = z.y.atan2(z.x) * self.power;
+            dr = r.powf(self.power - 1.) * self.power * dr + 1.;
+
+            let zr = r.powf(self.power);
+            z = Vec3::new(
+                theta.sin() * phi.cos(),
+                theta.sin() * phi.sin(),
+                theta.cos(),
+            ) * zr
+                + c;
+            r = z.norm();
+        }
+
+        if r < 1e-12 {
+            return 0.;
+        }
+        0.5 * r.ln() * r / dr * self.scale
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let r = Vec3::new(1.2, 1.2, 1.2) * self.scale;
+        Aabb::new(self.center - r, self.center + r)
+    }
+}
+
+// Makes any Sdf hittable by sphere tracing: step along the ray by the
+// distance bound until it drops below `epsilon` or `max_steps` runs out.
+pub struct SdfObject<'a, S: Sdf + ?Sized> {
+    pub sdf: &'a S,
+    pub material: Option<&'a dyn Material>,
+    pub max_steps: u32,
+    pub epsilon: f64,
+}
+
+impl<'a, S: Sdf + ?Sized> SdfObject<'a, S> {
+    pub fn new(sdf: &'a S, material: &'a dyn Material) -> SdfObject<'a, S> {
+        SdfObject {
+            sdf,
+            material: Some(material),
+            max_steps: 256,
+            epsilon: 1e-4,
+        }
+    }
+
+    // central differences of the distance field
+    fn estimate_normal(&self, p: &Point3) -> Vec3 {
+        let h = self.epsilon;
+        let dx = Vec3::new(h, 0., 0.);
+        let dy = Vec3::new(0., h, 0.);
+        let dz = Vec3::new(0., 0., h);
+
+        let gradient = Vec3::new(
+            self.sdf.distance(&(*p + dx)) - self.sdf.distance(&(*p - dx)),
+            self.sdf.distance(&(*p + dy)) - self.sdf.distance(&(*p - dy)),
+            self.sdf.distance(&(*p + dz)) - self.sdf.distance(&(*p - dz)),
+        );
+        Vec3::unit_vector(&gradient)
+    }
+}
+
+impl<'a, S: Sdf + ?Sized> Hittable<'a> for SdfObject<'a, S> {
+    fn hit(&self, ray: &Ray, ray_t: Interval, hit_record: &mut HitRecord<'a>) -> bool {
+        let inside = match self.sdf.bounding_box().hit(ray, ray_t) {
+            Some(inside) => inside,
+            None => return false,
+        };
+
+        // march in units of the ray parameter; abs() lets rays that start
+        // inside the shape (e.g. refracted rays) find their way out
+        let ray_length = ray.direction.norm();
+        let mut t = inside.min;
+        // a ray leaving the surface, such as a bounce or shadow ray, starts
+        // within epsilon of it and must get clear of that shell before
+        // anything counts as a hit; grazing rays take a while to
+        let mut leaving = inside.min <= ray_t.min;
+        for _ in 0..self.max_steps {
+            let distance = self.sdf.distance(&ray.at(t)).abs();
+            if distance >= self.epsilon {
+                leaving = false;
+            } else if !leaving {
+                if !ray_t.surrounds(t) {
+                    return false;
+                }
+
+                // Set hit record data
+                hit_record.t = t;
+                hit_record.p = ray.at(t);
+                let outward_normal = self.estimate_normal(&hit_record.p);
+                hit_record.set_face_normal(ray, &outward_normal);
+                hit_record.tangent = Vec3::default();
+                // spherical coordinates around the middle of the bounding box
+                let bbox = self.sdf.bounding_box();
+                let around = Vec3::unit_vector(&(hit_record.p - (bbox.min + bbox.max) / 2.));
+                (hit_record.u, hit_record.v) = Sphere::get_sphere_uv(&around);
+                hit_record.material = self.material;
+
+                return true;
+            }
+
+            t += f64::max(distance, self.epsilon) / ray_length;
+            if t > inside.max {
+                return false;
+            }
+        }
+
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::{
+        hittable::{HitRecord, Hittable},
+        interval::Interval,
+        point3::Point3,
+        ray::Ray,
+        sdf::{SdfObject, SdfSphere, SmoothUnion},
+        vec3::Vec3,
+    };
+
+    #[test]
+    fn test_grazing_ray_leaving_the_surface_hits_the_other_lobe() {
+        let left = SdfSphere {
+            center: Point3::new(0., 0., 0.),
+            radius: 1.,
+        };
+        let right = SdfSphere {
+            center: Point3::new(2.5, 0., 0.),
+            radius: 1.,
+        };
+        let union = SmoothUnion {
+            a: &left,
+            b: &right,
+            k: 0.1,
+        };
+        let object = SdfObject {
+            sdf: &union,
+            material: None,
+            max_steps: 256,
+            epsilon: 1e-4,
+        };
+
+        // from a point on the left lobe towards the right lobe's center,
+        // leaving the left lobe almost tangentially
+        let origin = Point3::new(0.41, f64::sqrt(1. - 0.41 * 0.41), 0.);
+        let direction = Vec3::unit_vector(&(right.center - origin));
+        assert!(Vec3::dot(&direction, &origin) < 0.02);
+
+        let mut hit_record = HitRecord::default();
+        let ray_t = Interval {
+            min: 0.001,
+            max: f64::INFINITY,
+        };
+        assert!(object.hit(&Ray::new(origin, direction), ray_t, &mut hit_record));
+        assert!((hit_record.t - ((right.center - origin).norm() - 1.)).abs() < 1e-3);
+        assert!((0. ..=1.).contains(&hit_record.u) && (0. ..=1.).contains(&hit_record.v));
+    }
+}
@@ -0,0 +1,251 @@
+use super::color::Color;
+
+// Wavelength range rendered in spectral mode, in nanometres
+pub const LAMBDA_MIN: f64 = 360.;
+pub const LAMBDA_MAX: f64 = 830.;
+
+// Three wavelengths carried along one path, stored where the RGB renderer
+// keeps its channels: in spectral mode a Color's x, y and z are the path's
+// values at lambda[0], lambda[1] and lambda[2]. The first is the hero
+// wavelength; the other two are spread evenly from it through the sampling
+// distribution, and get dropped when the path takes a direction only the
+// hero wavelength would (dispersion).
+#[derive(Copy, Clone)]
+pub struct SampledWavelengths {
+    pub lambda: [f64; 3],
+    pub pdf: [f64; 3],
+}
+
+impl SampledWavelengths {
+    // importance samples the wavelengths the eye is most sensitive to
+    // (Radziszewski et al.)
+    pub fn sample_visible(u: f64) -> SampledWavelengths {
+        let mut lambda = [0.; 3];
+        let mut pdf = [0.; 3];
+        for i in 0..3 {
+            let u = (u + i as f64 / 3.).fract();
+            lambda[i] = 538. - 138.888889 * (0.85691062 - 1.82750197 * u).atanh();
+            pdf[i] = visible_pdf(lambda[i]);
+        }
+        SampledWavelengths { lambda, pdf }
+    }
+
+    pub fn hero(&self) -> f64 {
+        self.lambda[0]
+    }
+
+    // keeps only the hero wavelength, which now stands for all three
+    pub fn terminate_secondary(&mut self) {
+        if self.pdf[1] == 0. {
+            return;
+        }
+        self.pdf[0] /= 3.;
+        self.pdf[1] = 0.;
+        self.pdf[2] = 0.;
+    }
+
+    // values of the spectrum Smits' method gives an RGB color (reflectance or
+    // emission) at the sampled wavelengths
+    pub fn upsample(&self, rgb: &Color) -> Color {
+        Color::new(
+            rgb_to_spectrum(rgb, self.lambda[0]),
+            rgb_to_spectrum(rgb, self.lambda[1]),
+            rgb_to_spectrum(rgb, self.lambda[2]),
+        )
+    }
+
+    // Monte Carlo estimate of the linear sRGB color of spectral radiance
+    // sampled at these wavelengths, white balanced so a flat spectrum of 1
+    // comes out as RGB white
+    pub fn to_rgb(&self, spectral: &Color) -> Color {
+        let values = [spectral.x, spectral.y, spectral.z];
+        let mut xyz = Color::zero();
+        for ((lambda, pdf), value) in self.lambda.iter().zip(self.pdf).zip(values) {
+            if pdf > 0. {
+                xyz += cie_xyz(*lambda) * (value / (3. * pdf));
+            }
+        }
+        let rgb = xyz_to_linear_srgb(&xyz);
+        let white = xyz_to_linear_srgb(&equal_energy_white());
+        Color::new(rgb.x / white.x, rgb.y / white.y, rgb.z / white.z)
+    }
+}
+
+fn visible_pdf(lambda: f64) -> f64 {
+    if !(LAMBDA_MIN..=LAMBDA_MAX).contains(&lambda) {
+        return 0.;
+    }
+    0.0039398042 / (0.0072 * (lambda - 538.)).cosh().powi(2)
+}
+
+// Wyman, Sloan and Shirley's multi-lobe Gaussian fit of the CIE 1931
+// standard observer
+fn cie_xyz(lambda: f64) -> Color {
+    let g = |mu: f64, sigma_below: f64, sigma_above: f64| {
+        let sigma = if lambda < mu {
+            sigma_below
+        } else {
+            sigma_above
+        };
+        (-0.5 * ((lambda - mu) / sigma).powi(2)).exp()
+    };
+    Color::new(
+        1.056 * g(599.8, 37.9, 31.0) + 0.362 * g(442.0, 16.0, 26.7) - 0.065 * g(501.1, 20.4, 26.2),
+        0.821 * g(568.8, 46.9, 40.5) + 0.286 * g(530.9, 16.3, 31.1),
+        1.217 * g(437.0, 11.8, 36.0) + 0.681 * g(459.0, 26.0, 13.8),
+    )
+}
+
+// XYZ of a flat spectrum of 1: each lobe of the fit integrates to its weight
+// times the average of its two widths times sqrt(2 pi)
+fn equal_energy_white() -> Color {
+    let lobe = |weight: f64, sigma_below: f64, sigma_above: f64| {
+        weight * 0.5 * (sigma_below + sigma_above) * (2. * std::f64::consts::PI).sqrt()
+    };
+    Color::new(
+        lobe(1.056, 37.9, 31.0) + lobe(0.362, 16.0, 26.7) - lobe(0.065, 20.4, 26.2),
+        lobe(0.821, 46.9, 40.5) + lobe(0.286, 16.3, 31.1),
+        lobe(1.217, 11.8, 36.0) + lobe(0.681, 26.0, 13.8),
+    )
+}
+
+fn xyz_to_linear_srgb(xyz: &Color) -> Color {
+    Color::new(
+        3.2404542 * xyz.x - 1.5371385 * xyz.y - 0.4985314 * xyz.z,
+        -0.9692660 * xyz.x + 1.8760108 * xyz.y + 0.0415560 * xyz.z,
+        0.0556434 * xyz.x - 0.2040259 * xyz.y + 1.0572252 * xyz.z,
+    )
+}
+
+// Smits' basis spectra, sampled at 10 evenly spaced wavelengths from 380 to
+// 720 nm
+const SMITS_WHITE: [f64; 10] = [
+    1.0000, 1.0000, 0.9999, 0.9993, 0.9992, 0.9998, 1.0000, 1.0000, 1.0000, 1.0000,
+];
+const SMITS_CYAN: [f64; 10] = [
+    0.9710, 0.9426, 1.0007, 1.0007, 1.0007, 1.0007, 0.1564, 0.0000, 0.0000, 0.0000,
+];
+const SMITS_MAGENTA: [f64; 10] = [
+    1.0000, 1.0000, 0.9685, 0.2229, 0.0000, 0.0458, 0.8369, 1.0000, 1.0000, 0.9959,
+];
+const SMITS_YELLOW: [f64; 10] = [
+    0.0001, 0.0000, 0.1088, 0.6651, 1.0000, 1.0000, 0.9996, 0.9586, 0.9685, 0.9840,
+];
+const SMITS_RED: [f64; 10] = [
+    0.1012, 0.0515, 0.0000, 0.0000, 0.0000, 0.0000, 0.8325, 1.0149, 1.0149, 1.0149,
+];
+const SMITS_GREEN: [f64; 10] = [
+    0.0000, 0.0000, 0.0273, 0.7937, 1.0000, 0.9418, 0.1719, 0.0000, 0.0000, 0.0025,
+];
+const SMITS_BLUE: [f64; 10] = [
+    1.0000, 1.0000, 0.8916, 0.3323, 0.0000, 0.0000, 0.0003, 0.0369, 0.0483, 0.0496,
+];
+
+fn smits_lookup(table: &[f64; 10], lambda: f64) -> f64 {
+    let x = ((lambda - 380.) / (720. - 380.) * 9.).clamp(0., 9.);
+    let i = (x as usize).min(8);
+    let t = x - i as f64;
+    table[i] * (1. - t) + table[i + 1] * t
+}
+
+// Smits' RGB to spectrum conversion: the smallest channel becomes white, the
+// next one the secondary color the two largest channels share, and what is
+// left the primary of the largest
+fn rgb_to_spectrum(rgb: &Color, lambda: f64) -> f64 {
+    let (r, g, b) = (rgb.x.max(0.), rgb.y.max(0.), rgb.z.max(0.));
+    let at = |table: &[f64; 10]| smits_lookup(table, lambda);
+
+    if r <= g && r <= b {
+        r * at(&SMITS_WHITE)
+            + if g <= b {
+                (g - r) * at(&SMITS_CYAN) + (b - g) * at(&SMITS_BLUE)
+            } else {
+                (b - r) * at(&SMITS_CYAN) + (g - b) * at(&SMITS_GREEN)
+            }
+    } else if g <= r && g <= b {
+        g * at(&SMITS_WHITE)
+            + if r <= b {
+                (r - g) * at(&SMITS_MAGENTA) + (b - r) * at(&SMITS_BLUE)
+            } else {
+                (b - g) * at(&SMITS_MAGENTA) + (r - b) * at(&SMITS_RED)
+            }
+    } else {
+        b * at(&SMITS_WHITE)
+            + if r <= g {
+                (r - b) * at(&SMITS_YELLOW) + (g - r) * at(&SMITS_GREEN)
+            } else {
+                (g - b) * at(&SMITS_YELLOW) + (r - g) * at(&SMITS_RED)
+            }
+    }
+}
+
+// Index of refraction that varies with wavelength, for glass that splits
+// white light into colors
+#[derive(Copy, Clone)]
+pub enum Dispersion {
+    // n = a + b / lambda^2, with lambda in micrometres
+    Cauchy { a: f64, b: f64 },
+    // n^2 = 1 + sum of b lambda^2 / (lambda^2 - c), with lambda in micrometres
+    Sellmeier { b: [f64; 3], c: [f64; 3] },
+}
+
+impl Dispersion {
+    // common optical crown glass
+    pub const BK7: Dispersion = Dispersion::Sellmeier {
+        b: [1.03961212, 0.231792344, 1.01046945],
+        c: [0.00600069867, 0.0200179144, 103.560653],
+    };
+    pub const FUSED_SILICA: Dispersion = Dispersion::Sellmeier {
+        b: [0.6961663, 0.4079426, 0.8974794],
+        c: [0.00467914826, 0.0135120631, 97.9340025],
+    };
+    // dense flint glass, about three times as dispersive as BK7
+    pub const SF11: Dispersion = Dispersion::Sellmeier {
+        b: [1.73759695, 0.313747346, 1.89878101],
+        c: [0.013188707, 0.0623068142, 155.23629],
+    };
+    pub const DIAMOND: Dispersion = Dispersion::Sellmeier {
+        b: [0.3306, 4.3356, 0.],
+        c: [0.030625, 0.011236, 0.],
+    };
+
+    pub fn ior(&self, lambda: f64) -> f64 {
+        let micrometres = lambda / 1000.;
+        let l2 = micrometres * micrometres;
+        match self {
+            Dispersion::Cauchy { a, b } => a + b / l2,
+            Dispersion::Sellmeier { b, c } => {
+                let sum: f64 = (0..3).map(|i| b[i] * l2 / (l2 - c[i])).sum();
+                (1. + sum).sqrt()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::{
+        color::Color,
+        spectrum::{Dispersion, SampledWavelengths},
+    };
+
+    #[test]
+    fn test_white_round_trip() {
+        // averaged over many wavelength samples, white stays white
+        let white = Color::new(1., 1., 1.);
+        let n = 10000;
+        let mut sum = Color::zero();
+        for k in 0..n {
+            let wavelengths = SampledWavelengths::sample_visible((k as f64 + 0.5) / n as f64);
+            sum += wavelengths.to_rgb(&wavelengths.upsample(&white));
+        }
+        let average = sum / n as f64;
+        assert!((average - white).norm() < 0.01);
+    }
+
+    #[test]
+    fn test_bk7_ior() {
+        // BK7 is 1.5168 at the sodium d line
+        assert!((Dispersion::BK7.ior(587.6) - 1.5168).abs() < 1e-4);
+    }
+}
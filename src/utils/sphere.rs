@@ -1,8 +1,10 @@
 use super::hittable::{HitRecord, Hittable};
 use super::interval::Interval;
 use super::material::Material;
+use super::onb::Onb;
 use super::point3::Point3;
 use super::ray::Ray;
+use super::sampler::Sampler;
 use super::vec3::Vec3;
 
 #[derive(Default)]
@@ -20,6 +22,29 @@ impl<'a> Sphere<'a> {
             material: None,
         }
     }
+
+    // uniform direction within the cone subtended by a sphere, around +z
+    fn random_to_sphere(radius: f64, distance_squared: f64, sampler: &mut dyn Sampler) -> Vec3 {
+        let (r1, r2) = sampler.get_2d();
+        let z = 1. + r2 * ((1. - radius * radius / distance_squared).sqrt() - 1.);
+
+        let phi = 2. * std::f64::consts::PI * r1;
+        let x = phi.cos() * (1. - z * z).sqrt();
+        let y = phi.sin() * (1. - z * z).sqrt();
+
+        Vec3::new(x, y, z)
+    }
+
+    // u: angle around the Y axis from X=-1, v: angle from Y=-1 to Y=+1, both in [0, 1]
+    pub fn get_sphere_uv(p: &Point3) -> (f64, f64) {
+        let theta = f64::acos(-p.y);
+        let phi = f64::atan2(-p.z, p.x) + std::f64::consts::PI;
+
+        (
+            phi / (2. * std::f64::consts::PI),
+            theta / std::f64::consts::PI,
+        )
+    }
 }
 
 impl<'a> Hittable<'a> for Sphere<'a> {
@@ -50,8 +75,60 @@ impl<'a> Hittable<'a> for Sphere<'a> {
         hit_record.p = ray.at(hit_record.t);
         let outward_normal = (hit_record.p - self.center) / self.radius;
         hit_record.set_face_normal(ray, &outward_normal);
+        (hit_record.u, hit_record.v) = Self::get_sphere_uv(&outward_normal);
+        hit_record.tangent = Vec3::new(outward_normal.z, 0., -outward_normal.x)
+            * (2. * std::f64::consts::PI * self.radius);
         hit_record.material = self.material;
 
         return true;
     }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        let mut hit_record = HitRecord::default();
+        let interval = Interval::bounded_interval(0.001, f64::INFINITY);
+        if !self.hit(&Ray::new(*origin, *direction), interval, &mut hit_record) {
+            return 0.;
+        }
+
+        // from inside, random() picks any direction
+        let distance_squared = (self.center - *origin).norm_squared();
+        if distance_squared <= self.radius * self.radius {
+            return 1. / (4. * std::f64::consts::PI);
+        }
+        let cos_theta_max = (1. - self.radius * self.radius / distance_squared).sqrt();
+        let solid_angle = 2. * std::f64::consts::PI * (1. - cos_theta_max);
+
+        1. / solid_angle
+    }
+
+    fn random(&self, origin: &Point3, sampler: &mut dyn Sampler) -> Vec3 {
+        let direction = self.center - *origin;
+        let distance_squared = direction.norm_squared();
+        if distance_squared <= self.radius * self.radius {
+            return Vec3::random_unit_vector(sampler);
+        }
+
+        let uvw = Onb::new(&direction);
+        uvw.transform(&Self::random_to_sphere(
+            self.radius,
+            distance_squared,
+            sampler,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::{hittable::Hittable, point3::Point3, sphere::Sphere, vec3::Vec3};
+
+    #[test]
+    fn test_pdf_value_from_inside() {
+        let sphere = Sphere {
+            center: Point3::new(0., 0., 0.),
+            radius: 1.,
+            material: None,
+        };
+        let pdf = sphere.pdf_value(&Point3::new(0.2, 0., 0.), &Vec3::new(0., 1., 0.));
+        assert!((pdf - 1. / (4. * std::f64::consts::PI)).abs() < 1e-12);
+    }
 }
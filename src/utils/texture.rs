@@ -0,0 +1,62 @@
+use super::{color::Color, hittable::HitRecord, point3::Point3};
+
+// A color that varies over a surface, looked up by the hit's surface
+// coordinates and position.
+pub trait Texture {
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color;
+}
+
+pub struct SolidColor {
+    pub color: Color,
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _u: f64, _v: f64, _p: &Point3) -> Color {
+        self.color
+    }
+}
+
+// 3D checkerboard of two other textures with cells `scale` units wide
+pub struct CheckerTexture<'a> {
+    pub scale: f64,
+    pub even: &'a dyn Texture,
+    pub odd: &'a dyn Texture,
+}
+
+impl Texture for CheckerTexture<'_> {
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color {
+        let cell = |x: f64| (x / self.scale).floor() as i64;
+        if (cell(p.x) + cell(p.y) + cell(p.z)) % 2 == 0 {
+            self.even.value(u, v, p)
+        } else {
+            self.odd.value(u, v, p)
+        }
+    }
+}
+
+// A material parameter that is either fixed or read from a texture. Scalar
+// parameters use the red channel.
+#[derive(Copy, Clone)]
+pub enum TextureParam<'a> {
+    Constant(Color),
+    Texture(&'a dyn Texture),
+}
+
+impl<'a> TextureParam<'a> {
+    pub fn scalar(value: f64) -> TextureParam<'a> {
+        TextureParam::Constant(Color::new(value, value, value))
+    }
+
+    pub fn color(&self, hit_record: &HitRecord) -> Color {
+        match self {
+            TextureParam::Constant(color) => *color,
+            TextureParam::Texture(texture) => {
+                texture.value(hit_record.u, hit_record.v, &hit_record.p)
+            }
+        }
+    }
+
+    pub fn value(&self, hit_record: &HitRecord) -> f64 {
+        self.color(hit_record).x
+    }
+}
@@ -1,4 +1,4 @@
-use rand::Rng;
+use super::sampler::Sampler;
 use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
 
 #[derive(Copy, Clone, Default)]
@@ -9,7 +9,7 @@ pub struct Vec3 {
 }
 
 impl Vec3 {
-    pub fn new(x: f64, y: f64, z: f64) -> Self {
+    pub const fn new(x: f64, y: f64, z: f64) -> Self {
         Vec3 { x, y, z }
     }
 
@@ -29,6 +29,10 @@ impl Vec3 {
         self.x * self.x + self.y * self.y + self.z * self.z
     }
 
+    pub fn max_component(&self) -> f64 {
+        f64::max(self.x, f64::max(self.y, self.z))
+    }
+
     pub fn near_zero(&self) -> bool {
         let small_value = 1e-8;
         return self.x.abs() < small_value
@@ -40,31 +44,34 @@ impl Vec3 {
         return *vector / vector.norm();
     }
 
-    pub fn random_coordinate(min: f64, max: f64) -> f64 {
-        let mut rng = rand::thread_rng();
-        return rng.gen::<f64>() * (max - min) + min;
+    pub fn random_coordinate(sampler: &mut dyn Sampler, min: f64, max: f64) -> f64 {
+        sampler.get_1d() * (max - min) + min
     }
 
-    fn random_vector(min: f64, max: f64) -> Vec3 {
-        Vec3 {
-            x: Self::random_coordinate(min, max),
-            y: Self::random_coordinate(min, max),
-            z: Self::random_coordinate(min, max),
-        }
+    // uniform on the sphere, mapped from a single 2D sample
+    pub fn random_unit_vector(sampler: &mut dyn Sampler) -> Vec3 {
+        let (u1, u2) = sampler.get_2d();
+        let z = 1. - 2. * u1;
+        let r = f64::max(0., 1. - z * z).sqrt();
+        let phi = 2. * std::f64::consts::PI * u2;
+
+        Vec3::new(r * phi.cos(), r * phi.sin(), z)
     }
 
-    pub fn random_unit_vector() -> Vec3 {
-        loop {
-            let p = Self::random_vector(-1., 1.);
-            let lensq = p.norm_squared();
-            if 1e-160 <= lensq && lensq <= 1. {
-                return p / lensq.sqrt();
-            }
-        }
+    // cosine-weighted direction around +z
+    pub fn random_cosine_direction(sampler: &mut dyn Sampler) -> Vec3 {
+        let (r1, r2) = sampler.get_2d();
+
+        let phi = 2. * std::f64::consts::PI * r1;
+        let x = phi.cos() * r2.sqrt();
+        let y = phi.sin() * r2.sqrt();
+        let z = (1. - r2).sqrt();
+
+        Vec3::new(x, y, z)
     }
 
-    pub fn random_on_hemisphere(normal: &Vec3) -> Vec3 {
-        let on_unit_sphere = Self::random_unit_vector();
+    pub fn random_on_hemisphere(normal: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        let on_unit_sphere = Self::random_unit_vector(sampler);
         if Self::dot(&on_unit_sphere, normal) > 0. {
             return on_unit_sphere;
         } else {
@@ -0,0 +1,233 @@
+use super::hittable::{HitRecord, Hittable};
+use super::interval::Interval;
+use super::material::{Material, SubsurfaceMedium};
+use super::point3::Point3;
+use super::ray::Ray;
+use super::sampler::{hash, IndependentSampler, Sampler};
+use super::vec3::Vec3;
+use super::voxel_grid::VoxelGrid;
+
+// Hittable::hit has no sampler to draw from, so tracking gets a stream of its
+// own seeded by the ray's seed and the ray itself; the same ray always sees
+// the same medium
+fn ray_sampler(ray: &Ray) -> IndependentSampler {
+    IndependentSampler::new(hash(&[
+        ray.seed,
+        ray.origin.x.to_bits(),
+        ray.origin.y.to_bits(),
+        ray.origin.z.to_bits(),
+        ray.direction.x.to_bits(),
+        ray.direction.y.to_bits(),
+        ray.direction.z.to_bits(),
+    ]))
+}
+
+// A participating medium whose density is read from a voxel grid. Collisions
+// are found with delta tracking against the grid's maximum density, so the
+// free-flight distances are unbiased however the density varies.
+pub struct HeterogeneousVolume<'a> {
+    pub density: &'a VoxelGrid,
+    pub density_scale: f64,
+    pub phase_function: Option<&'a dyn Material>,
+}
+
+impl<'a> HeterogeneousVolume<'a> {
+    pub fn new(
+        density: &'a VoxelGrid,
+        density_scale: f64,
+        phase_function: &'a dyn Material,
+    ) -> HeterogeneousVolume<'a> {
+        HeterogeneousVolume {
+            density,
+            density_scale,
+            phase_function: Some(phase_function),
+        }
+    }
+
+    fn majorant(&self) -> f64 {
+        self.density.max_value() * self.density_scale
+    }
+
+    // distance (in ray parameter units) to the next tentative collision
+    fn free_flight(&self, ray_length: f64, sampler: &mut dyn Sampler) -> f64 {
+        let u = sampler.get_1d();
+        -(1. - u).ln() / (self.majorant() * ray_length)
+    }
+}
+
+impl<'a> Hittable<'a> for HeterogeneousVolume<'a> {
+    fn hit(&self, ray: &Ray, ray_t: Interval, hit_record: &mut HitRecord<'a>) -> bool {
+        let majorant = self.majorant();
+        if majorant <= 0. {
+            return false;
+        }
+        let inside = match self.density.bounds.hit(ray, ray_t) {
+            Some(inside) => inside,
+            None => return false,
+        };
+
+        // delta tracking: accept a tentative collision with probability
+        // density / majorant, otherwise keep walking
+        let ray_length = ray.direction.norm();
+        let mut sampler = ray_sampler(ray);
+        let mut t = inside.min;
+        loop {
+            t += self.free_flight(ray_length, &mut sampler);
+            if t >= inside.max {
+                return false;
+            }
+            let density = self.density.lookup(&ray.at(t)) * self.density_scale;
+            if sampler.get_1d() * majorant < density {
+                break;
+            }
+        }
+
+        hit_record.t = t;
+        hit_record.p = ray.at(t);
+        hit_record.normal = Vec3::new(1., 0., 0.); // arbitrary
+        hit_record.geometric_normal = hit_record.normal;
+        hit_record.tangent = Vec3::default();
+        hit_record.front_face = true; // also arbitrary
+        hit_record.in_medium = true;
+        hit_record.material = self.phase_function;
+
+        true
+    }
+
+    // shadow rays see through the medium, dimmed by a ratio tracking
+    // estimate of its transmittance rather than stopped at a collision
+    fn hit_opaque(&self, _ray: &Ray, _ray_t: Interval, _hit_record: &mut HitRecord<'a>) -> bool {
+        false
+    }
+
+    fn transmittance(&self, ray: &Ray, ray_t: Interval) -> f64 {
+        let majorant = self.majorant();
+        let inside = match self.density.bounds.hit(ray, ray_t) {
+            Some(inside) if majorant > 0. => inside,
+            _ => return 1.,
+        };
+
+        let ray_length = ray.direction.norm();
+        let mut sampler = ray_sampler(ray);
+        let mut transmittance = 1.;
+        let mut t = inside.min;
+        loop {
+            t += self.free_flight(ray_length, &mut sampler);
+            if t >= inside.max {
+                return transmittance;
+            }
+            let density = self.density.lookup(&ray.at(t)) * self.density_scale;
+            transmittance *= 1. - density / majorant;
+        }
+    }
+}
+
+// Random-walk subsurface scattering inside a closed `boundary`. The boundary
+// keeps its own material, normally a Dielectric or RoughDielectric, which
+// refracts light in and out. A ray whose next boundary hit is a back face is
+// travelling inside, and gets a free-flight distance sampled against the
+// medium; if that comes first, the ray collides with `medium` there instead.
+// Anything else inside the boundary is ignored by the walk.
+pub struct Subsurface<'a> {
+    pub boundary: &'a dyn Hittable<'a>,
+    pub medium: &'a SubsurfaceMedium,
+}
+
+impl<'a> Hittable<'a> for Subsurface<'a> {
+    fn hit(&self, ray: &Ray, ray_t: Interval, hit_record: &mut HitRecord<'a>) -> bool {
+        if !self.boundary.hit(ray, ray_t, hit_record) {
+            return false;
+        }
+        let majorant = self.medium.majorant();
+        if hit_record.front_face || majorant <= 0. {
+            return true;
+        }
+
+        let u = ray_sampler(ray).get_1d();
+        let t = ray_t.min - (1. - u).ln() / (majorant * ray.direction.norm());
+        if t >= hit_record.t {
+            return true;
+        }
+
+        hit_record.t = t;
+        hit_record.p = ray.at(t);
+        hit_record.normal = -Vec3::unit_vector(&ray.direction); // arbitrary
+        hit_record.geometric_normal = hit_record.normal;
+        hit_record.tangent = Vec3::default();
+        hit_record.front_face = true; // also arbitrary
+        hit_record.in_medium = true;
+        hit_record.material = Some(self.medium);
+
+        true
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        self.boundary.pdf_value(origin, direction)
+    }
+
+    fn random(&self, origin: &Point3, sampler: &mut dyn Sampler) -> Vec3 {
+        self.boundary.random(origin, sampler)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::{
+        aabb::Aabb,
+        color::Color,
+        hittable::{Hittable, HittableList},
+        integrator::{Integrator, IterativePathTracer, PathTracer, Scene},
+        material::Isotropic,
+        point3::Point3,
+        ray::Ray,
+        sampler::{IndependentSampler, Sampler},
+        sphere::Sphere,
+        vec3::Vec3,
+        volume::HeterogeneousVolume,
+        voxel_grid::VoxelGrid,
+    };
+
+    #[test]
+    fn test_emissive_medium_radiance() {
+        // deep inside an emissive medium that scatters half of what it
+        // collides with, emission and absorption balance out at `emission`
+        let bounds = Aabb::new(Point3::new(-50., -50., -50.), Point3::new(50., 50., 50.));
+        let density = VoxelGrid::new(1, 1, 1, bounds, vec![1.]).unwrap();
+        let phase_function = Isotropic {
+            albedo: Color::new(0.5, 0.5, 0.5),
+            emission: Color::new(1., 2., 3.),
+            emission_grid: None,
+        };
+        let medium = HeterogeneousVolume::new(&density, 1., &phase_function);
+        let mut world: HittableList<dyn Hittable> = HittableList::default();
+        world.add(&medium);
+        // a light to sample that gives off nothing, so MIS weights come into
+        // play without adding any light
+        let light = Sphere {
+            center: Point3::new(0., 0., -2.),
+            radius: 1.,
+            material: None,
+        };
+        let mut lights: HittableList<dyn Hittable> = HittableList::default();
+        lights.add(&light);
+        let scene = Scene {
+            world: &world,
+            lights: &lights,
+            background: Some(Color::zero()),
+            max_depth: 40,
+        };
+
+        let integrators: [&dyn Integrator; 2] = [&PathTracer, &IterativePathTracer::default()];
+        for integrator in integrators {
+            let mut sampler = IndependentSampler::new(17);
+            let paths = 20000;
+            let mut total = Color::zero();
+            for _ in 0..paths {
+                let direction = Vec3::new(sampler.get_1d() - 0.5, sampler.get_1d() - 0.5, -1.);
+                total += integrator.li(&Ray::new(Point3::zero(), direction), &scene, &mut sampler);
+            }
+            let average = total / paths as f64;
+            assert!((average - phase_function.emission).norm() < 0.05);
+        }
+    }
+}
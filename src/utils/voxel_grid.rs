@@ -0,0 +1,235 @@
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::path::Path;
+
+use super::aabb::Aabb;
+use super::point3::Point3;
+
+// A scalar field sampled on a regular 3D grid stretched over `bounds`.
+// Values are stored x-fastest, then y, then z, and are treated as sitting
+// at voxel centers.
+pub struct VoxelGrid {
+    pub nx: usize,
+    pub ny: usize,
+    pub nz: usize,
+    pub bounds: Aabb,
+    data: Vec<f64>,
+    max_value: f64,
+}
+
+impl VoxelGrid {
+    pub fn new(nx: usize, ny: usize, nz: usize, bounds: Aabb, data: Vec<f64>) -> Result<VoxelGrid> {
+        if nx == 0 || ny == 0 || nz == 0 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("empty {}x{}x{} voxel grid", nx, ny, nz),
+            ));
+        }
+        if data.len() != nx * ny * nz {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "voxel data does not match grid size",
+            ));
+        }
+        let max_value = data.iter().cloned().fold(0., f64::max);
+
+        Ok(VoxelGrid {
+            nx,
+            ny,
+            nz,
+            bounds,
+            data,
+            max_value,
+        })
+    }
+
+    // builds a grid by evaluating `f` at every voxel center
+    pub fn from_fn<F: Fn(Point3) -> f64>(
+        nx: usize,
+        ny: usize,
+        nz: usize,
+        bounds: Aabb,
+        f: F,
+    ) -> Result<VoxelGrid> {
+        let extent = bounds.max - bounds.min;
+        let mut data = Vec::with_capacity(nx * ny * nz);
+        for k in 0..nz {
+            for j in 0..ny {
+                for i in 0..nx {
+                    let p = Point3::new(
+                        bounds.min.x + extent.x * (i as f64 + 0.5) / nx as f64,
+                        bounds.min.y + extent.y * (j as f64 + 0.5) / ny as f64,
+                        bounds.min.z + extent.z * (k as f64 + 0.5) / nz as f64,
+                    );
+                    data.push(f(p));
+                }
+            }
+        }
+
+        VoxelGrid::new(nx, ny, nz, bounds, data)
+    }
+
+    // headerless little-endian f32 values, x-fastest
+    pub fn load_raw<P: AsRef<Path>>(
+        path: P,
+        nx: usize,
+        ny: usize,
+        nz: usize,
+        bounds: Aabb,
+    ) -> Result<VoxelGrid> {
+        let bytes = fs::read(path)?;
+        if bytes.len() != nx * ny * nz * 4 {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "expected {} bytes for a {}x{}x{} grid, found {}",
+                    nx * ny * nz * 4,
+                    nx,
+                    ny,
+                    nz,
+                    bytes.len()
+                ),
+            ));
+        }
+
+        let data = bytes
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64)
+            .collect();
+        VoxelGrid::new(nx, ny, nz, bounds, data)
+    }
+
+    // Mitsuba-style `.vol` file: "VOL", version byte (3), i32 encoding
+    // (1 = f32, 3 = u8), i32 resolution x/y/z, i32 channel count, six f32
+    // bounds (min then max), then the data. Only the first channel is kept.
+    pub fn load_vol<P: AsRef<Path>>(path: P) -> Result<VoxelGrid> {
+        let bytes = fs::read(path)?;
+        let invalid = |message: &str| Error::new(ErrorKind::InvalidData, message.to_string());
+
+        if bytes.len() < 48 || &bytes[0..3] != b"VOL" {
+            return Err(invalid("not a .vol file"));
+        }
+        if bytes[3] != 3 {
+            return Err(invalid("unsupported .vol version"));
+        }
+
+        let read_i32 = |offset: usize| {
+            i32::from_le_bytes([
+                bytes[offset],
+                bytes[offset + 1],
+                bytes[offset + 2],
+                bytes[offset + 3],
+            ])
+        };
+        let read_f32 = |offset: usize| {
+            f32::from_le_bytes([
+                bytes[offset],
+                bytes[offset + 1],
+                bytes[offset + 2],
+                bytes[offset + 3],
+            ]) as f64
+        };
+
+        let encoding = read_i32(4);
+        let (nx, ny, nz) = (read_i32(8), read_i32(12), read_i32(16));
+        let channels = read_i32(20);
+        if nx <= 0 || ny <= 0 || nz <= 0 || channels <= 0 {
+            return Err(invalid("invalid .vol dimensions"));
+        }
+        let (nx, ny, nz, channels) = (nx as usize, ny as usize, nz as usize, channels as usize);
+
+        let bounds = Aabb::new(
+            Point3::new(read_f32(24), read_f32(28), read_f32(32)),
+            Point3::new(read_f32(36), read_f32(40), read_f32(44)),
+        );
+
+        let count = nx * ny * nz;
+        let payload = &bytes[48..];
+        let data: Vec<f64> = match encoding {
+            1 => {
+                if payload.len() < count * channels * 4 {
+                    return Err(invalid("truncated .vol data"));
+                }
+                (0..count)
+                    .map(|i| read_f32(48 + i * channels * 4))
+                    .collect()
+            }
+            3 => {
+                if payload.len() < count * channels {
+                    return Err(invalid("truncated .vol data"));
+                }
+                (0..count)
+                    .map(|i| payload[i * channels] as f64 / 255.)
+                    .collect()
+            }
+            _ => return Err(invalid("unsupported .vol encoding")),
+        };
+
+        VoxelGrid::new(nx, ny, nz, bounds, data)
+    }
+
+    pub fn max_value(&self) -> f64 {
+        self.max_value
+    }
+
+    fn voxel(&self, i: usize, j: usize, k: usize) -> f64 {
+        self.data[(k * self.ny + j) * self.nx + i]
+    }
+
+    // trilinear interpolation between voxel centers; zero outside the bounds
+    pub fn lookup(&self, p: &Point3) -> f64 {
+        if !self.bounds.contains(p) {
+            return 0.;
+        }
+
+        let extent = self.bounds.max - self.bounds.min;
+        let gx = (p.x - self.bounds.min.x) / extent.x * self.nx as f64 - 0.5;
+        let gy = (p.y - self.bounds.min.y) / extent.y * self.ny as f64 - 0.5;
+        let gz = (p.z - self.bounds.min.z) / extent.z * self.nz as f64 - 0.5;
+
+        let (i0, i1, fx) = Self::cell(gx, self.nx);
+        let (j0, j1, fy) = Self::cell(gy, self.ny);
+        let (k0, k1, fz) = Self::cell(gz, self.nz);
+
+        let lerp = |a: f64, b: f64, t: f64| a * (1. - t) + b * t;
+        let c00 = lerp(self.voxel(i0, j0, k0), self.voxel(i1, j0, k0), fx);
+        let c10 = lerp(self.voxel(i0, j1, k0), self.voxel(i1, j1, k0), fx);
+        let c01 = lerp(self.voxel(i0, j0, k1), self.voxel(i1, j0, k1), fx);
+        let c11 = lerp(self.voxel(i0, j1, k1), self.voxel(i1, j1, k1), fx);
+
+        lerp(lerp(c00, c10, fy), lerp(c01, c11, fy), fz)
+    }
+
+    // neighbouring indices and blend factor along one axis, clamped at the edges
+    fn cell(g: f64, n: usize) -> (usize, usize, f64) {
+        let g = g.clamp(0., (n - 1) as f64);
+        let i0 = g.floor() as usize;
+        let i1 = usize::min(i0 + 1, n - 1);
+        (i0, i1, g - i0 as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::aabb::Aabb;
+    use crate::utils::point3::Point3;
+    use crate::utils::voxel_grid::VoxelGrid;
+
+    #[test]
+    fn test_trilinear_lookup() {
+        let bounds = Aabb::new(Point3::new(0., 0., 0.), Point3::new(2., 1., 1.));
+        let grid = VoxelGrid::new(2, 1, 1, bounds, vec![0., 1.]).unwrap();
+
+        assert_eq!(grid.lookup(&Point3::new(0.5, 0.5, 0.5)), 0.);
+        assert_eq!(grid.lookup(&Point3::new(1.0, 0.5, 0.5)), 0.5);
+        assert_eq!(grid.lookup(&Point3::new(1.5, 0.5, 0.5)), 1.);
+        assert_eq!(grid.lookup(&Point3::new(3.0, 0.5, 0.5)), 0.);
+    }
+
+    #[test]
+    fn test_empty_grid_is_rejected() {
+        let bounds = Aabb::new(Point3::new(0., 0., 0.), Point3::new(1., 1., 1.));
+        assert!(VoxelGrid::new(0, 1, 1, bounds, vec![]).is_err());
+        assert!(VoxelGrid::from_fn(4, 0, 4, bounds, |_| 1.).is_err());
+    }
+}